@@ -0,0 +1,20 @@
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+
+    // 开启 ffi-fixture 时编译 middleware::ffi 测试使用的 C 实现；
+    // 只输出搜索路径，由测试模块自行链接。普通构建不需要 C 编译器。
+    #[cfg(feature = "ffi-fixture")]
+    {
+        println!("cargo::rerun-if-changed=include/umrouter.h");
+        println!("cargo::rerun-if-changed=tests/ffi/vip_guard.c");
+        cc::Build::new()
+            .file("tests/ffi/vip_guard.c")
+            .include("include")
+            .cargo_metadata(false)
+            .compile("umrouter_ffi_fixture");
+        println!(
+            "cargo::rustc-link-search=native={}",
+            std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo")
+        );
+    }
+}
@@ -0,0 +1,45 @@
+/*
+ * umrouter C ABI：宿主语言通过函数指针表实现 Matcher / Executor。
+ *
+ * 上下文与执行结果均为 NUL 结尾的 UTF-8 JSON，格式见
+ * src/umrouter_core/middleware/json.rs。
+ *
+ * 所有权与线程约定：
+ * - user_data 归 Rust 侧适配器所有，适配器析构时调用一次 drop（可为 NULL）
+ * - 回调可能在任意线程、并发地调用，user_data 必须线程安全
+ * - context_json 只在本次调用期间有效
+ * - execute 返回的字符串由宿主分配，Rust 拷贝后立即调用 free_result 归还；
+ *   返回 NULL 视为 continue
+ * - 回调不得向 Rust 抛出异常
+ */
+#ifndef UMROUTER_H
+#define UMROUTER_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct UmMatcherVTable {
+    void *user_data;
+    /* 必须非 NULL。 */
+    bool (*matches)(void *user_data, const char *context_json);
+    void (*drop)(void *user_data);
+    const char *name;
+} UmMatcherVTable;
+
+typedef struct UmExecutorVTable {
+    void *user_data;
+    /* 必须非 NULL。 */
+    char *(*execute)(void *user_data, const char *context_json);
+    void (*free_result)(void *user_data, char *result);
+    void (*drop)(void *user_data);
+    const char *name;
+} UmExecutorVTable;
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* UMROUTER_H */
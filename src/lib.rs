@@ -1,5 +1,9 @@
 mod umrouter_core;
 
+// 对外公开路由核心的全部类型（中间件、pipeline、路由表、状态机），
+// 文档测试与 FFI 宿主都通过 crate 根路径使用它们。
+pub use umrouter_core::*;
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -4,6 +4,9 @@ mod route;
 mod state;
 mod types;
 
+#[cfg(test)]
+mod test_support;
+
 pub use middleware::*;
 pub use pipeline::*;
 pub use route::*;
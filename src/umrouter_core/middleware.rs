@@ -1,5 +1,28 @@
+mod async_executor;
+mod builtin;
+mod expr;
+mod failure;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+mod json;
+mod matchers;
+mod ordering;
+mod protected;
 mod registry;
 mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use async_executor::*;
+pub use builtin::*;
+pub use expr::*;
+pub use failure::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+pub use matchers::*;
+pub use protected::*;
 pub use registry::*;
 pub use types::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
@@ -0,0 +1,82 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use super::types::{ExecuteContext, Executor, MiddlewareResult};
+
+/// 异步 executor 返回的 future 类型。
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 异步 Executor trait：用于鉴权检查、远端开关、确认弹窗等异步逻辑。
+///
+/// 不绑定任何具体的 async runtime，由 pipeline runner 负责驱动。
+pub trait AsyncExecutor: Send + Sync {
+    /// 执行中间件逻辑，返回一个 future。
+    fn execute<'a, 'c: 'a>(
+        &'a self,
+        ctx: &'a mut ExecuteContext<'c>,
+    ) -> BoxFuture<'a, MiddlewareResult>;
+
+    /// 可选：返回一个描述性名称，用于调试。
+    fn name(&self) -> &str {
+        "unnamed_async_executor"
+    }
+}
+
+/// 中间件的执行器：同步或异步。
+///
+/// 同一条中间件链中可以混合两种执行器，runner 会按链的顺序依次驱动。
+#[derive(Clone)]
+pub enum MiddlewareExecutor {
+    /// 同步执行器。
+    Sync(Arc<dyn Executor>),
+
+    /// 异步执行器。
+    Async(Arc<dyn AsyncExecutor>),
+}
+
+impl MiddlewareExecutor {
+    /// 包装一个同步执行器。
+    pub fn sync(executor: impl Executor + 'static) -> Self {
+        Self::Sync(Arc::new(executor))
+    }
+
+    /// 包装一个异步执行器。
+    pub fn asynchronous(executor: impl AsyncExecutor + 'static) -> Self {
+        Self::Async(Arc::new(executor))
+    }
+
+    /// 执行器的描述性名称。
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Sync(executor) => executor.name(),
+            Self::Async(executor) => executor.name(),
+        }
+    }
+
+    /// 是否为异步执行器。
+    pub fn is_async(&self) -> bool {
+        matches!(self, Self::Async(_))
+    }
+}
+
+impl std::fmt::Debug for MiddlewareExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sync(executor) => f.debug_tuple("Sync").field(&executor.name()).finish(),
+            Self::Async(executor) => f.debug_tuple("Async").field(&executor.name()).finish(),
+        }
+    }
+}
+
+impl From<Arc<dyn Executor>> for MiddlewareExecutor {
+    fn from(executor: Arc<dyn Executor>) -> Self {
+        Self::Sync(executor)
+    }
+}
+
+impl From<Arc<dyn AsyncExecutor>> for MiddlewareExecutor {
+    fn from(executor: Arc<dyn AsyncExecutor>) -> Self {
+        Self::Async(executor)
+    }
+}
@@ -0,0 +1,7 @@
+mod auth;
+mod dedup;
+mod experiment;
+
+pub use auth::*;
+pub use dedup::*;
+pub use experiment::*;
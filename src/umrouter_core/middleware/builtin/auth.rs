@@ -0,0 +1,365 @@
+use std::sync::{Arc, Mutex};
+
+use crate::umrouter_core::middleware::{
+    ExecuteContext, Executor, FailurePolicy, Middleware, MiddlewareExecutor, MiddlewarePhase,
+    MiddlewareResult, TagMatcher,
+};
+use crate::umrouter_core::pipeline::NavRequest;
+use crate::umrouter_core::types::MiddlewareId;
+
+/// 默认的需要登录标签。
+pub const AUTH_REQUIRED_TAG: &str = "auth-required";
+
+/// 默认的仅游客可见标签（例如登录页、注册页）。
+pub const GUEST_ONLY_TAG: &str = "guest-only";
+
+/// 登录态提供者，由业务实现。
+pub trait SessionProvider: Send + Sync {
+    /// 当前是否存在有效会话。
+    fn has_session(&self) -> bool;
+}
+
+/// 被登录守卫拦截、等待恢复的导航请求。
+///
+/// 同一时刻只保留最近一次被拦截的请求。
+#[derive(Debug, Clone, Default)]
+pub struct PendingNavigation {
+    slot: Arc<Mutex<Option<NavRequest>>>,
+}
+
+impl PendingNavigation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 保存被拦截的请求，覆盖之前未处理的请求。
+    pub fn store(&self, request: NavRequest) {
+        *self.lock() = Some(request);
+    }
+
+    /// 查看待恢复的请求。
+    pub fn peek(&self) -> Option<NavRequest> {
+        self.lock().clone()
+    }
+
+    /// 取出待恢复的请求，用于登录成功后重新发起导航。
+    pub fn resume(&self) -> Option<NavRequest> {
+        self.lock().take()
+    }
+
+    /// 丢弃待恢复的请求（例如用户取消登录）。
+    pub fn discard(&self) {
+        self.lock().take();
+    }
+
+    /// 是否有待恢复的请求。
+    pub fn is_pending(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<NavRequest>> {
+        self.slot.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 根据当前执行上下文还原出被拦截的导航请求（保留参数、目标栈与导航方式）。
+///
+/// 展示模式 / 动画与路由默认值不同时（调用方指定或被此前的中间件改写），一并带上。
+fn intercepted_request(ctx: &ExecuteContext) -> NavRequest {
+    let mut request = NavRequest::by_name(ctx.route.name.clone())
+        .with_params(ctx.params.clone())
+        .with_stack(ctx.target_stack.clone());
+    if *ctx.transition != ctx.route.transition_spec {
+        request = request
+            .with_presentation(ctx.transition.presentation)
+            .with_transition(ctx.transition.clone());
+    }
+    request
+}
+
+//
+// ========== 登录守卫 ==========
+//
+
+/// 登录守卫。
+///
+/// 带有 `required_tag`（默认 "auth-required"）的路由在没有会话时重定向到登录页，
+/// 原始请求保存在 `PendingNavigation` 中，并作为重定向的 continuation 一并返回。
+/// 登录成功后调用 `resume()` 取回原请求重新导航。
+///
+/// AuthGuard 可以克隆：注册到注册表的中间件与业务持有的句柄共享同一份待恢复请求。
+#[derive(Clone)]
+pub struct AuthGuard {
+    session: Arc<dyn SessionProvider>,
+    login: NavRequest,
+    required_tag: String,
+    pending: PendingNavigation,
+}
+
+impl AuthGuard {
+    /// 中间件 id。
+    pub const ID: &'static str = "auth_guard";
+
+    /// 创建登录守卫，`login` 为没有会话时跳转的请求。
+    pub fn new(session: Arc<dyn SessionProvider>, login: NavRequest) -> Self {
+        Self {
+            session,
+            login,
+            required_tag: AUTH_REQUIRED_TAG.into(),
+            pending: PendingNavigation::new(),
+        }
+    }
+
+    /// 设置需要登录的路由标签。
+    pub fn with_required_tag(mut self, tag: impl Into<String>) -> Self {
+        self.required_tag = tag.into();
+        self
+    }
+
+    /// 与其他组件（例如 GuestOnlyGuard）共享待恢复请求。
+    pub fn with_pending(mut self, pending: PendingNavigation) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    /// 待恢复请求的存储。
+    pub fn pending(&self) -> &PendingNavigation {
+        &self.pending
+    }
+
+    /// 取出待恢复的请求。
+    pub fn resume(&self) -> Option<NavRequest> {
+        self.pending.resume()
+    }
+
+    /// 丢弃待恢复的请求。
+    pub fn discard(&self) {
+        self.pending.discard();
+    }
+
+    /// 生成可注册的中间件（PreRW 阶段，按标签匹配）。
+    ///
+    /// 安全守卫使用 fail-closed：登录态查询 panic 时中止导航，且不会被熔断自动停用。
+    pub fn middleware(&self) -> Middleware {
+        Middleware::new(
+            MiddlewareId(Self::ID.into()),
+            Arc::new(TagMatcher::new(self.required_tag.clone())),
+            MiddlewareExecutor::sync(self.clone()),
+            MiddlewarePhase::PreRW,
+        )
+        .with_failure_policy(FailurePolicy::FailClosed)
+    }
+}
+
+impl Executor for AuthGuard {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        if self.session.has_session() {
+            return MiddlewareResult::Continue;
+        }
+        let original = intercepted_request(ctx);
+        self.pending.store(original.clone());
+        MiddlewareResult::redirect_then_resume(self.login.clone(), original)
+    }
+
+    fn name(&self) -> &str {
+        Self::ID
+    }
+}
+
+impl std::fmt::Debug for AuthGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthGuard")
+            .field("login", &self.login)
+            .field("required_tag", &self.required_tag)
+            .field("pending", &self.pending.peek())
+            .finish()
+    }
+}
+
+//
+// ========== 游客守卫 ==========
+//
+
+/// 游客守卫。
+///
+/// 带有 `guest_tag`（默认 "guest-only"）的路由在已有会话时不再展示：
+/// 有待恢复请求时恢复该请求，否则重定向到 `home`。
+#[derive(Clone)]
+pub struct GuestOnlyGuard {
+    session: Arc<dyn SessionProvider>,
+    home: NavRequest,
+    guest_tag: String,
+    pending: Option<PendingNavigation>,
+}
+
+impl GuestOnlyGuard {
+    /// 中间件 id。
+    pub const ID: &'static str = "guest_only_guard";
+
+    /// 创建游客守卫，`home` 为已登录时跳转的请求。
+    pub fn new(session: Arc<dyn SessionProvider>, home: NavRequest) -> Self {
+        Self {
+            session,
+            home,
+            guest_tag: GUEST_ONLY_TAG.into(),
+            pending: None,
+        }
+    }
+
+    /// 设置仅游客可见的路由标签。
+    pub fn with_guest_tag(mut self, tag: impl Into<String>) -> Self {
+        self.guest_tag = tag.into();
+        self
+    }
+
+    /// 已登录时优先恢复登录守卫保存的请求。
+    pub fn with_pending(mut self, pending: PendingNavigation) -> Self {
+        self.pending = Some(pending);
+        self
+    }
+
+    /// 生成可注册的中间件（PreRW 阶段，按标签匹配，fail-closed）。
+    pub fn middleware(&self) -> Middleware {
+        Middleware::new(
+            MiddlewareId(Self::ID.into()),
+            Arc::new(TagMatcher::new(self.guest_tag.clone())),
+            MiddlewareExecutor::sync(self.clone()),
+            MiddlewarePhase::PreRW,
+        )
+        .with_failure_policy(FailurePolicy::FailClosed)
+    }
+}
+
+impl Executor for GuestOnlyGuard {
+    fn execute(&self, _ctx: &mut ExecuteContext) -> MiddlewareResult {
+        if !self.session.has_session() {
+            return MiddlewareResult::Continue;
+        }
+        let target = self
+            .pending
+            .as_ref()
+            .and_then(PendingNavigation::resume)
+            .unwrap_or_else(|| self.home.clone());
+        MiddlewareResult::redirect(target)
+    }
+
+    fn name(&self) -> &str {
+        Self::ID
+    }
+}
+
+impl std::fmt::Debug for GuestOnlyGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GuestOnlyGuard")
+            .field("home", &self.home)
+            .field("guest_tag", &self.guest_tag)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::umrouter_core::middleware::MiddlewareRegistry;
+    use crate::umrouter_core::pipeline::{
+        NavTarget, NavigationContext, PipelineResult, PipelineRunner,
+    };
+    use crate::umrouter_core::test_support::{block_on, route};
+    use crate::umrouter_core::types::{CanonicalParams, PresentationMode};
+
+    #[derive(Default)]
+    struct FlagSession(AtomicBool);
+
+    impl SessionProvider for FlagSession {
+        fn has_session(&self) -> bool {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn run(registry: &MiddlewareRegistry, nav: &mut NavigationContext) -> PipelineResult {
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        block_on(PipelineRunner::new(registry).run(&chain, nav)).result
+    }
+
+    #[test]
+    fn redirects_to_login_and_resumes_after_login() {
+        let session = Arc::new(FlagSession::default());
+        let auth = AuthGuard::new(session.clone(), NavRequest::by_name("auth.login"));
+        let guest = GuestOnlyGuard::new(session.clone(), NavRequest::by_name("home.index"))
+            .with_pending(auth.pending().clone());
+
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(auth.middleware()).unwrap();
+        registry.register(guest.middleware()).unwrap();
+
+        let orders = route("orders.detail", "/orders/:id", &[AUTH_REQUIRED_TAG]);
+        let mut params = CanonicalParams::default();
+        params.map.insert("id".into(), "42".into());
+        let mut nav = NavigationContext::new(&orders, params.clone());
+        nav.transition.presentation = PresentationMode::Sheet;
+        let PipelineResult::Redirected {
+            request,
+            continuation,
+            ..
+        } = run(&registry, &mut nav)
+        else {
+            panic!("expected redirect to login");
+        };
+        assert_eq!(request.target, NavTarget::Name("auth.login".into()));
+        assert_eq!(continuation.unwrap().params.map, params.map);
+        assert!(auth.pending().is_pending());
+
+        // 未登录时可以访问登录页。
+        let login = route("auth.login", "/login", &[GUEST_ONLY_TAG]);
+        let mut nav = NavigationContext::new(&login, CanonicalParams::default());
+        assert!(matches!(
+            run(&registry, &mut nav),
+            PipelineResult::Completed
+        ));
+
+        // 登录成功后再次进入登录页：恢复原请求。
+        session.0.store(true, Ordering::SeqCst);
+        let mut nav = NavigationContext::new(&login, CanonicalParams::default());
+        let PipelineResult::Redirected { request, .. } = run(&registry, &mut nav) else {
+            panic!("expected resume");
+        };
+        assert_eq!(request.target, NavTarget::Name("orders.detail".into()));
+        assert_eq!(request.params.map, params.map);
+        assert_eq!(request.presentation, Some(PresentationMode::Sheet));
+        assert!(auth.resume().is_none());
+
+        let mut nav = NavigationContext::new(&orders, params);
+        assert!(matches!(
+            run(&registry, &mut nav),
+            PipelineResult::Completed
+        ));
+    }
+
+    struct BrokenSession;
+
+    impl SessionProvider for BrokenSession {
+        fn has_session(&self) -> bool {
+            panic!("session store unavailable")
+        }
+    }
+
+    #[test]
+    fn fails_closed_when_session_provider_panics() {
+        let auth = AuthGuard::new(Arc::new(BrokenSession), NavRequest::by_name("auth.login"));
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(auth.middleware()).unwrap();
+
+        let orders = route("orders.detail", "/orders/:id", &[AUTH_REQUIRED_TAG]);
+        // 超过熔断阈值后仍然阻断导航。
+        for _ in 0..5 {
+            let mut nav = NavigationContext::new(&orders, CanonicalParams::default());
+            let PipelineResult::Failed(failure) = run(&registry, &mut nav) else {
+                panic!("auth guard must fail closed");
+            };
+            assert_eq!(failure.id.0, AuthGuard::ID);
+        }
+        assert!(!registry.is_tripped(&MiddlewareId(AuthGuard::ID.into())));
+    }
+}
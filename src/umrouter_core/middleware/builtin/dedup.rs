@@ -0,0 +1,361 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::umrouter_core::middleware::{
+    AlwaysMatcher, ExecuteContext, Executor, Middleware, MiddlewareExecutor, MiddlewarePhase,
+    MiddlewareResult,
+};
+use crate::umrouter_core::pipeline::{OutcomeHooks, PipelineResult};
+use crate::umrouter_core::types::{CanonicalParams, MiddlewareId, RouteId, StackId};
+
+//
+// ========== 时钟 ==========
+//
+
+/// 时钟抽象，便于在测试中控制时间。
+pub trait Clock: Send + Sync {
+    /// 当前时间。
+    fn now(&self) -> Instant;
+}
+
+/// 系统单调时钟。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+//
+// ========== 重复导航守卫 ==========
+//
+
+/// 重复导航的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateMode {
+    /// 作为错误拒绝。
+    #[default]
+    Reject,
+
+    /// 合并到已接受的导航：同样中止，但调用方应视为成功、不报错。
+    Merge,
+}
+
+/// 拒绝重复导航时的中止原因。
+pub const DUPLICATE_REJECTED: &str = "DuplicateNavigation";
+
+/// 合并重复导航时的中止原因。
+pub const DUPLICATE_MERGED: &str = "DuplicateNavigation: merged";
+
+/// 判定"相同导航"的指纹：路由、参数、目标栈都相同。
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    route: RouteId,
+    params: CanonicalParams,
+    stack: StackId,
+}
+
+/// 已接受的导航：通过检查时即占用窗口，`ticket` 用于撤销这一次预留。
+#[derive(Debug)]
+struct Accepted {
+    fingerprint: Fingerprint,
+    at: Instant,
+    ticket: u64,
+}
+
+#[derive(Debug, Default)]
+struct AcceptedLog {
+    entries: Vec<Accepted>,
+    next_ticket: u64,
+}
+
+/// 一次执行中的预留：导航完成时提交，中止、重定向、失败或被丢弃时撤销。
+struct Reservation {
+    log: Arc<Mutex<AcceptedLog>>,
+    ticket: u64,
+    settled: AtomicBool,
+}
+
+impl Reservation {
+    fn settle(&self, result: &PipelineResult) {
+        if self.settled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        if !matches!(result, PipelineResult::Completed) {
+            self.release();
+        }
+    }
+
+    fn release(&self) {
+        lock(&self.log)
+            .entries
+            .retain(|entry| entry.ticket != self.ticket);
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !*self.settled.get_mut() {
+            self.release();
+        }
+    }
+}
+
+/// 重复导航守卫：抑制连点造成的重复 push。
+///
+/// 在 `window` 内与已接受的导航完全相同（RouteId、CanonicalParams、目标 StackId）
+/// 的请求会被中止，中止原因为 `DUPLICATE_REJECTED` 或 `DUPLICATE_MERGED`。
+///
+/// 通过检查的导航立即以当时的时间占用窗口，因此并发执行中的相同导航也会被拦截；
+/// 预留通过 `OutcomeHooks` 与本次链的执行结果绑定：完成时保留，
+/// 被后续中间件中止、重定向、失败或导航被丢弃时撤销，被拦截的请求不会延长窗口。
+#[derive(Clone)]
+pub struct DuplicateNavigationGuard {
+    window: Duration,
+    mode: DuplicateMode,
+    clock: Arc<dyn Clock>,
+    accepted: Arc<Mutex<AcceptedLog>>,
+}
+
+impl DuplicateNavigationGuard {
+    /// 中间件 id。
+    pub const ID: &'static str = "duplicate_navigation_guard";
+
+    /// 使用系统时钟创建守卫。
+    pub fn new(window: Duration, mode: DuplicateMode) -> Self {
+        Self::with_clock(window, mode, Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建守卫。
+    pub fn with_clock(window: Duration, mode: DuplicateMode, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            window,
+            mode,
+            clock,
+            accepted: Arc::default(),
+        }
+    }
+
+    /// 生成可注册的中间件：PreRW 阶段、对所有路由生效，并排在最前面执行。
+    pub fn middleware(&self) -> Middleware {
+        Middleware::new(
+            MiddlewareId(Self::ID.into()),
+            Arc::new(AlwaysMatcher),
+            MiddlewareExecutor::sync(self.clone()),
+            MiddlewarePhase::PreRW,
+        )
+        .with_priority(i32::MIN)
+    }
+
+    /// 执行结果是否为被合并的重复导航（调用方应静默处理）。
+    pub fn is_merged(result: &PipelineResult) -> bool {
+        matches!(
+            result,
+            PipelineResult::Aborted { by, reason } if by.0 == Self::ID && reason == DUPLICATE_MERGED
+        )
+    }
+
+    /// 清空已记录的导航。
+    pub fn reset(&self) {
+        lock(&self.accepted).entries.clear();
+    }
+}
+
+fn lock(log: &Mutex<AcceptedLog>) -> std::sync::MutexGuard<'_, AcceptedLog> {
+    log.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Executor for DuplicateNavigationGuard {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        let now = self.clock.now();
+        let fingerprint = Fingerprint {
+            route: ctx.route.id,
+            params: ctx.params.clone(),
+            stack: ctx.target_stack.clone(),
+        };
+
+        let mut log = lock(&self.accepted);
+        log.entries
+            .retain(|entry| now.duration_since(entry.at) < self.window);
+        if log
+            .entries
+            .iter()
+            .any(|entry| entry.fingerprint == fingerprint)
+        {
+            let reason = match self.mode {
+                DuplicateMode::Reject => DUPLICATE_REJECTED,
+                DuplicateMode::Merge => DUPLICATE_MERGED,
+            };
+            return MiddlewareResult::Abort {
+                reason: reason.into(),
+            };
+        }
+        let ticket = log.next_ticket;
+        log.next_ticket += 1;
+        log.entries.push(Accepted {
+            fingerprint,
+            at: now,
+            ticket,
+        });
+        drop(log);
+
+        let reservation = Reservation {
+            log: self.accepted.clone(),
+            ticket,
+            settled: AtomicBool::new(false),
+        };
+        let mut hooks = ctx
+            .extensions
+            .get::<OutcomeHooks>()
+            .cloned()
+            .unwrap_or_default();
+        hooks.push(move |result| reservation.settle(result));
+        ctx.extensions.insert(hooks);
+        MiddlewareResult::Continue
+    }
+
+    fn name(&self) -> &str {
+        Self::ID
+    }
+}
+
+impl std::fmt::Debug for DuplicateNavigationGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuplicateNavigationGuard")
+            .field("window", &self.window)
+            .field("mode", &self.mode)
+            .field("accepted", &lock(&self.accepted).entries.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::umrouter_core::middleware::{AsyncExecutor, BoxFuture, MiddlewareRegistry};
+    use crate::umrouter_core::pipeline::{NavigationContext, PipelineRunner};
+    use crate::umrouter_core::test_support::{ManualClock, block_on, route};
+
+    /// 在 `open` 之前一直挂起，之后中止带 `blocked` 参数的导航。
+    struct Gate(Arc<AtomicBool>);
+
+    impl AsyncExecutor for Gate {
+        fn execute<'a, 'c: 'a>(
+            &'a self,
+            ctx: &'a mut ExecuteContext<'c>,
+        ) -> BoxFuture<'a, MiddlewareResult> {
+            Box::pin(async move {
+                std::future::poll_fn(|_| {
+                    if self.0.load(Ordering::Acquire) {
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    }
+                })
+                .await;
+                if ctx.params.map.contains_key("blocked") {
+                    MiddlewareResult::Abort {
+                        reason: "blocked".into(),
+                    }
+                } else {
+                    MiddlewareResult::Continue
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn suppresses_identical_navigation_within_window() {
+        let clock = Arc::new(ManualClock::new());
+        let guard = DuplicateNavigationGuard::with_clock(
+            Duration::from_millis(500),
+            DuplicateMode::Merge,
+            clock.clone(),
+        );
+        let open = Arc::new(AtomicBool::new(true));
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(guard.middleware()).unwrap();
+        registry
+            .register(Middleware::new(
+                MiddlewareId("gate".into()),
+                Arc::new(AlwaysMatcher),
+                MiddlewareExecutor::asynchronous(Gate(open.clone())),
+                MiddlewarePhase::PreRW,
+            ))
+            .unwrap();
+
+        let meta = route("orders.detail", "/orders/:id", &[]);
+        let params = |id: &str| {
+            let mut params = CanonicalParams::default();
+            params.map.insert("id".into(), id.into());
+            params
+        };
+        let run = |params: CanonicalParams| async {
+            let mut nav = NavigationContext::new(&meta, params);
+            let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+            PipelineRunner::new(&registry)
+                .run(&chain, &mut nav)
+                .await
+                .result
+        };
+
+        assert!(matches!(
+            block_on(run(params("1"))),
+            PipelineResult::Completed
+        ));
+        assert!(DuplicateNavigationGuard::is_merged(&block_on(run(params(
+            "1"
+        )))));
+        // 参数不同不算重复。
+        assert!(matches!(
+            block_on(run(params("2"))),
+            PipelineResult::Completed
+        ));
+
+        clock.advance(Duration::from_millis(499));
+        assert!(DuplicateNavigationGuard::is_merged(&block_on(run(params(
+            "1"
+        )))));
+        clock.advance(Duration::from_millis(1));
+        assert!(matches!(
+            block_on(run(params("1"))),
+            PipelineResult::Completed
+        ));
+
+        // 执行中的导航已占用窗口：与其重叠的相同导航被拦截；
+        // 第一次导航随后被中止时撤销预留，重试时仍然执行。
+        let mut blocked = params("3");
+        blocked.map.insert("blocked".into(), true.into());
+        open.store(false, Ordering::Release);
+        let mut first = pin!(run(blocked.clone()));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(DuplicateNavigationGuard::is_merged(&block_on(run(
+            blocked.clone()
+        ))));
+        open.store(true, Ordering::Release);
+        for result in [block_on(first), block_on(run(blocked))] {
+            assert!(matches!(
+                result,
+                PipelineResult::Aborted { ref by, .. } if by.0 == "gate"
+            ));
+        }
+
+        // 被丢弃的导航同样撤销预留。
+        open.store(false, Ordering::Release);
+        let mut dropped = Box::pin(run(params("4")));
+        assert!(dropped.as_mut().poll(&mut cx).is_pending());
+        drop(dropped);
+        open.store(true, Ordering::Release);
+        assert!(matches!(
+            block_on(run(params("4"))),
+            PipelineResult::Completed
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+
+use crate::umrouter_core::middleware::{
+    AlwaysMatcher, ExecuteContext, Executor, Matcher, Middleware, MiddlewareExecutor,
+    MiddlewarePhase, MiddlewareResult,
+};
+use crate::umrouter_core::pipeline::NavRequest;
+use crate::umrouter_core::types::{MiddlewareId, RuntimeKind};
+
+/// 提供稳定的用户标识，用于实验分桶。
+pub trait UserKeyProvider: Send + Sync {
+    /// 当前用户的稳定标识；返回 None 时不参与任何实验。
+    fn user_key(&self) -> Option<String>;
+}
+
+/// 实验分组命中后对导航的改写。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantTarget {
+    /// 不改写（对照组）。
+    Unchanged,
+
+    /// 改为导航到另一个路由（按路由名），参数与目标栈保持不变。
+    Route(String),
+
+    /// 同一路由改由另一个 runtime 渲染。
+    Runtime(RuntimeKind),
+}
+
+/// 实验分组。
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// 分组名，例如 "control" / "new_checkout"。
+    pub name: String,
+
+    /// 流量权重。
+    pub weight: u32,
+
+    /// 命中后的改写。
+    pub target: VariantTarget,
+}
+
+/// 实验定义。
+///
+/// 所有分组权重之和为 0 时实验不会分配任何用户。
+#[derive(Clone)]
+pub struct Experiment {
+    /// 实验 key，参与分桶哈希，修改会导致用户重新分桶。
+    pub key: String,
+
+    /// 决定实验作用于哪些导航。
+    pub matcher: Arc<dyn Matcher>,
+
+    /// 分组（按声明顺序切分桶区间）。
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    pub fn new(key: impl Into<String>, matcher: impl Matcher + 'static) -> Self {
+        Self {
+            key: key.into(),
+            matcher: Arc::new(matcher),
+            variants: Vec::new(),
+        }
+    }
+
+    /// 追加一个分组。
+    pub fn with_variant(
+        mut self,
+        name: impl Into<String>,
+        weight: u32,
+        target: VariantTarget,
+    ) -> Self {
+        self.variants.push(Variant {
+            name: name.into(),
+            weight,
+            target,
+        });
+        self
+    }
+
+    /// 按用户标识确定分组：同一用户在同一实验中始终得到同一分组。
+    pub fn assign(&self, user_key: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut bucket = fnv1a(&[self.key.as_bytes(), b":", user_key.as_bytes()]) % total;
+        self.variants.iter().find(|v| {
+            let weight = u64::from(v.weight);
+            if bucket < weight {
+                true
+            } else {
+                bucket -= weight;
+                false
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for Experiment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Experiment")
+            .field("key", &self.key)
+            .field("matcher", &self.matcher.name())
+            .field("variants", &self.variants)
+            .finish()
+    }
+}
+
+/// 一次实验分配结果，用于曝光上报。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExperimentAssignment {
+    /// 实验 key。
+    pub experiment: String,
+
+    /// 命中的分组名。
+    pub variant: String,
+}
+
+/// 本次导航中的所有实验分配。
+///
+/// 同时以 "experiment.{key}" -> 分组名 写入字符串表，便于跨语言读取。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExperimentAssignments(pub Vec<ExperimentAssignment>);
+
+/// A/B 实验中间件。
+///
+/// 对每个命中的实验按 FNV-1a(实验 key + 用户标识) 分桶：
+/// - 分配结果写入 Extensions（`ExperimentAssignments` 与字符串表）
+/// - `Runtime` 分组直接改写本次导航的 runtime
+/// - `Route` 分组重定向到变体路由（携带原参数、目标栈与扩展数据，分配结果不会丢失）；
+///   多个实验都要求改写路由时，以先声明的实验为准
+/// - 扩展数据中已有某个实验的分配时（例如重定向后的导航）沿用该分组，不重复记录
+///
+/// 需要以 ReadWrite 模式注册，通常放在登录守卫之后。
+#[derive(Clone)]
+pub struct ExperimentMiddleware {
+    user_keys: Arc<dyn UserKeyProvider>,
+    experiments: Arc<Vec<Experiment>>,
+}
+
+impl ExperimentMiddleware {
+    /// 中间件 id。
+    pub const ID: &'static str = "ab_experiment";
+
+    pub fn new(user_keys: Arc<dyn UserKeyProvider>, experiments: Vec<Experiment>) -> Self {
+        Self {
+            user_keys,
+            experiments: Arc::new(experiments),
+        }
+    }
+
+    /// 生成可注册的中间件（PreRW 阶段）。
+    ///
+    /// 各实验的 matcher 只在 executor 中执行一次，中间件本身对所有导航生效。
+    pub fn middleware(&self) -> Middleware {
+        Middleware::new(
+            MiddlewareId(Self::ID.into()),
+            Arc::new(AlwaysMatcher),
+            MiddlewareExecutor::sync(self.clone()),
+            MiddlewarePhase::PreRW,
+        )
+    }
+}
+
+impl Executor for ExperimentMiddleware {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        let Some(user_key) = self.user_keys.user_key() else {
+            return MiddlewareResult::Continue;
+        };
+
+        let mut assignments = ctx
+            .extensions
+            .get::<ExperimentAssignments>()
+            .cloned()
+            .unwrap_or_default();
+        let mut redirect_to = None;
+        for experiment in self.experiments.iter() {
+            if !experiment.matcher.matches(&ctx.match_context()) {
+                continue;
+            }
+            let assigned = assignments
+                .0
+                .iter()
+                .find(|a| a.experiment == experiment.key)
+                .map(|a| a.variant.clone());
+            let variant = match &assigned {
+                Some(name) => experiment.variants.iter().find(|v| v.name == *name),
+                None => experiment.assign(&user_key),
+            };
+            let Some(variant) = variant else {
+                continue;
+            };
+
+            if assigned.is_none() {
+                ctx.extensions.insert_str(
+                    format!("experiment.{}", experiment.key),
+                    variant.name.clone(),
+                );
+                assignments.0.push(ExperimentAssignment {
+                    experiment: experiment.key.clone(),
+                    variant: variant.name.clone(),
+                });
+            }
+            match &variant.target {
+                VariantTarget::Unchanged => {}
+                VariantTarget::Runtime(runtime) => ctx.runtime = *runtime,
+                // 已经在变体路由上时不再重定向，避免循环。
+                VariantTarget::Route(name) if *name == ctx.route.name => {}
+                VariantTarget::Route(name) => {
+                    redirect_to.get_or_insert_with(|| name.clone());
+                }
+            }
+        }
+        ctx.extensions.insert(assignments);
+
+        let Some(name) = redirect_to else {
+            return MiddlewareResult::Continue;
+        };
+        // 与原请求保持一致：参数、目标栈、调用方指定的展示方式与已完成的分配都带到变体路由。
+        let mut request = NavRequest::by_name(name)
+            .with_params(ctx.params.clone())
+            .with_stack(ctx.target_stack.clone())
+            .with_extensions(ctx.extensions.clone());
+        if *ctx.transition != ctx.route.transition_spec {
+            request = request
+                .with_presentation(ctx.transition.presentation)
+                .with_transition(ctx.transition.clone());
+        }
+        MiddlewareResult::redirect(request)
+    }
+
+    fn name(&self) -> &str {
+        Self::ID
+    }
+}
+
+impl std::fmt::Debug for ExperimentMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExperimentMiddleware")
+            .field("experiments", &self.experiments)
+            .finish()
+    }
+}
+
+/// 64 位 FNV-1a 哈希：实现简单、跨平台跨语言结果一致。
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(OFFSET, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::middleware::{MiddlewareRegistry, NameGlobMatcher};
+    use crate::umrouter_core::pipeline::{
+        NavTarget, NavigationContext, PipelineResult, PipelineRunner,
+    };
+    use crate::umrouter_core::test_support::{block_on, route};
+    use crate::umrouter_core::types::{CanonicalParams, PresentationMode};
+
+    struct FixedUser(String);
+
+    impl UserKeyProvider for FixedUser {
+        fn user_key(&self) -> Option<String> {
+            Some(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn buckets_deterministically_and_rewrites_target() {
+        assert_eq!(fnv1a(&[b"a"]), 0xaf63_dc4c_8601_ec8c);
+
+        let checkout = Experiment::new("checkout_v2", NameGlobMatcher::new("cart.checkout"))
+            .with_variant("control", 1, VariantTarget::Unchanged)
+            .with_variant("new", 1, VariantTarget::Route("cart.checkout_v2".into()));
+        let counts = (0..1000).fold([0; 2], |mut counts, i| {
+            let variant = checkout.assign(&format!("user-{i}")).unwrap();
+            counts[usize::from(variant.name == "new")] += 1;
+            counts
+        });
+        assert!(counts.iter().all(|&c| c > 400), "{counts:?}");
+        let user = (0..)
+            .map(|i| format!("user-{i}"))
+            .find(|u| checkout.assign(u).unwrap().name == "new")
+            .unwrap();
+        assert_eq!(checkout.assign(&user).unwrap().name, "new");
+
+        let flutter = Experiment::new("detail_flutter", NameGlobMatcher::new("orders.*"))
+            .with_variant("flutter", 1, VariantTarget::Runtime(RuntimeKind::Flutter));
+        let experiments =
+            ExperimentMiddleware::new(Arc::new(FixedUser(user)), vec![checkout, flutter]);
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(experiments.middleware()).unwrap();
+
+        let meta = route("cart.checkout", "/cart/checkout", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        nav.transition.presentation = PresentationMode::Modal;
+        let modal = nav.transition.clone();
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        let PipelineResult::Redirected { request, .. } = outcome.result else {
+            panic!("expected redirect to variant route");
+        };
+        assert_eq!(request.target, NavTarget::Name("cart.checkout_v2".into()));
+        assert_eq!(request.presentation, Some(PresentationMode::Modal));
+        assert_eq!(request.transition.as_ref(), Some(&modal));
+
+        // 分配结果随重定向请求进入变体路由的导航，且不会重复记录。
+        let variant = route("cart.checkout_v2", "/cart/checkout/v2", &[]);
+        let mut nav = NavigationContext::from_request(&variant, request);
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(nav.transition, modal);
+        assert_eq!(
+            nav.extensions.get_str("experiment.checkout_v2"),
+            Some("new")
+        );
+        assert_eq!(
+            nav.extensions.get::<ExperimentAssignments>().unwrap().0,
+            [ExperimentAssignment {
+                experiment: "checkout_v2".into(),
+                variant: "new".into(),
+            }]
+        );
+
+        let meta = route("orders.detail", "/orders/:id", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(nav.runtime, RuntimeKind::Flutter);
+        assert_eq!(
+            nav.extensions.get::<ExperimentAssignments>().unwrap().0,
+            [ExperimentAssignment {
+                experiment: "detail_flutter".into(),
+                variant: "flutter".into(),
+            }]
+        );
+    }
+}
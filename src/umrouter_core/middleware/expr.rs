@@ -0,0 +1,706 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use super::matchers::{
+    AllTagsMatcher, AndMatcher, AnyTagsMatcher, NameGlobMatcher, NotMatcher, OrMatcher,
+    ParamMatcher, PathPrefixMatcher, RuntimeMatcher, StackMatcher, TagMatcher, glob_match,
+};
+use super::types::{AlwaysMatcher, FnMatcher, MatchContext, Matcher, MatcherDependency};
+use crate::umrouter_core::types::{RuntimeKind, StackId};
+
+/// 表达式编译错误。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    /// 出错位置（源码中的字节偏移）。
+    pub position: usize,
+
+    /// 错误描述。
+    pub message: String,
+}
+
+impl ExprError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// 由表达式编译得到的 Matcher。
+///
+/// 用于通过远端配置挂载中间件，而无需发版 Rust 代码。例如：
+///
+/// ```text
+/// tag("auth-required") && !runtime(Flutter) && name ~ "orders.*"
+/// ```
+///
+/// 语法：
+///
+/// ```text
+/// expr    := and ( "||" and )*
+/// and     := unary ( "&&" unary )*
+/// unary   := "!" unary | primary
+/// primary := "(" expr ")" | "true" | "false" | call | compare
+/// call    := ident "(" [ literal ( "," literal )* ] ")"
+/// compare := ( "name" | "path" ) ( "~" | "==" | "!=" ) string
+/// ```
+///
+/// 内建函数：
+/// - `tag("x")` / `all_tags("a", "b")` / `any_tags("a", "b")`
+/// - `runtime(Native | ReactNative | Flutter)`
+/// - `stack("home")`
+/// - `path_prefix("/account")`
+/// - `param("key")` / `param("key", <literal>)`
+///
+/// `~` 表示 glob 匹配（`*` / `?`），`==` / `!=` 表示精确比较。
+///
+/// `name()` 返回表达式源码，便于在链路调试输出中定位配置。
+pub struct ExprMatcher {
+    source: String,
+    compiled: Arc<dyn Matcher>,
+}
+
+impl ExprMatcher {
+    /// 编译一条表达式。
+    pub fn compile(source: impl Into<String>) -> Result<Self, ExprError> {
+        let source = source.into();
+        let tokens = tokenize(&source)?;
+        let compiled = Parser { tokens, pos: 0 }.parse()?;
+        Ok(Self { source, compiled })
+    }
+
+    /// 表达式源码。
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl Matcher for ExprMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        self.compiled.matches(ctx)
+    }
+
+    fn name(&self) -> &str {
+        &self.source
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        self.compiled.dependency()
+    }
+}
+
+impl fmt::Debug for ExprMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExprMatcher")
+            .field("source", &self.source)
+            .field("compiled", &self.compiled.name())
+            .finish()
+    }
+}
+
+/// 已编译表达式的缓存。
+///
+/// 远端配置下发时，同一条表达式通常会被多个中间件复用，
+/// 缓存后每次导航只需执行编译好的 matcher 树。
+#[derive(Default)]
+pub struct ExprCache {
+    compiled: Mutex<HashMap<String, Arc<ExprMatcher>>>,
+}
+
+impl ExprCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取已编译的表达式；未命中时编译并缓存。
+    ///
+    /// 编译失败的表达式不会被缓存。
+    pub fn get_or_compile(&self, source: &str) -> Result<Arc<ExprMatcher>, ExprError> {
+        let mut compiled = self.compiled.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(matcher) = compiled.get(source) {
+            return Ok(Arc::clone(matcher));
+        }
+        let matcher = Arc::new(ExprMatcher::compile(source)?);
+        compiled.insert(source.to_owned(), Arc::clone(&matcher));
+        Ok(matcher)
+    }
+
+    /// 缓存的表达式数量。
+    pub fn len(&self) -> usize {
+        self.compiled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// 是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 清空缓存。
+    pub fn clear(&self) {
+        self.compiled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+impl fmt::Debug for ExprCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExprCache")
+            .field("count", &self.len())
+            .finish()
+    }
+}
+
+//
+// ========== 词法分析 ==========
+//
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(serde_json::Number),
+    LParen,
+    RParen,
+    Comma,
+    Not,
+    AndAnd,
+    OrOr,
+    Tilde,
+    EqEq,
+    NotEq,
+    Eof,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("identifier `{name}`"),
+            Token::Str(s) => format!("string {s:?}"),
+            Token::Number(n) => format!("number {n}"),
+            Token::LParen => "`(`".into(),
+            Token::RParen => "`)`".into(),
+            Token::Comma => "`,`".into(),
+            Token::Not => "`!`".into(),
+            Token::AndAnd => "`&&`".into(),
+            Token::OrOr => "`||`".into(),
+            Token::Tilde => "`~`".into(),
+            Token::EqEq => "`==`".into(),
+            Token::NotEq => "`!=`".into(),
+            Token::Eof => "end of input".into(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        // 双字符运算符。
+        let two = source[pos..].get(..2);
+        let op = match two {
+            Some("&&") => Some(Token::AndAnd),
+            Some("||") => Some(Token::OrOr),
+            Some("==") => Some(Token::EqEq),
+            Some("!=") => Some(Token::NotEq),
+            _ => None,
+        };
+        if let Some(op) = op {
+            chars.next();
+            chars.next();
+            tokens.push((pos, op));
+            continue;
+        }
+
+        let single = match c {
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            ',' => Some(Token::Comma),
+            '!' => Some(Token::Not),
+            '~' => Some(Token::Tilde),
+            _ => None,
+        };
+        if let Some(single) = single {
+            chars.next();
+            tokens.push((pos, single));
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            chars.next();
+            let mut value = String::new();
+            let mut closed = false;
+            while let Some((esc_pos, ch)) = chars.next() {
+                match ch {
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, other @ ('\\' | '"' | '\''))) => value.push(other),
+                        Some((_, other)) => {
+                            return Err(ExprError::new(
+                                esc_pos,
+                                format!("unknown escape sequence `\\{other}`"),
+                            ));
+                        }
+                        None => break,
+                    },
+                    ch if ch == c => {
+                        closed = true;
+                        break;
+                    }
+                    ch => value.push(ch),
+                }
+            }
+            if !closed {
+                return Err(ExprError::new(pos, "unterminated string literal"));
+            }
+            tokens.push((pos, Token::Str(value)));
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '-' {
+            let mut end = pos;
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_ascii_digit() || ch == '.' || (i == pos && ch == '-') {
+                    end = i + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let text = &source[pos..end];
+            let number = text
+                .parse::<i64>()
+                .ok()
+                .map(serde_json::Number::from)
+                .or_else(|| {
+                    text.parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                })
+                .ok_or_else(|| ExprError::new(pos, format!("invalid number `{text}`")))?;
+            tokens.push((pos, Token::Number(number)));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut end = pos;
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    end = i + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push((pos, Token::Ident(source[pos..end].to_owned())));
+            continue;
+        }
+
+        return Err(ExprError::new(pos, format!("unexpected character `{c}`")));
+    }
+
+    tokens.push((source.len(), Token::Eof));
+    Ok(tokens)
+}
+
+//
+// ========== 语法分析 ==========
+//
+
+/// 表达式允许的最大嵌套深度（括号与 `!` 各计一层）。
+pub const MAX_EXPR_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<Arc<dyn Matcher>, ExprError> {
+        let matcher = self.parse_or(0)?;
+        match self.peek() {
+            Token::Eof => Ok(matcher),
+            other => Err(self.error(format!("expected end of input, found {}", other.describe()))),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].1
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.pos].0
+    }
+
+    fn advance(&mut self) -> (usize, Token) {
+        let token = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: impl Into<String>) -> ExprError {
+        ExprError::new(self.position(), message)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExprError> {
+        if *self.peek() == expected {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "expected {}, found {}",
+                expected.describe(),
+                self.peek().describe()
+            )))
+        }
+    }
+
+    fn parse_or(&mut self, depth: usize) -> Result<Arc<dyn Matcher>, ExprError> {
+        let mut operands = vec![self.parse_and(depth)?];
+        while *self.peek() == Token::OrOr {
+            self.advance();
+            operands.push(self.parse_and(depth)?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Arc::new(OrMatcher::new(operands))
+        })
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<Arc<dyn Matcher>, ExprError> {
+        let mut operands = vec![self.parse_unary(depth)?];
+        while *self.peek() == Token::AndAnd {
+            self.advance();
+            operands.push(self.parse_unary(depth)?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Arc::new(AndMatcher::new(operands))
+        })
+    }
+
+    fn parse_unary(&mut self, depth: usize) -> Result<Arc<dyn Matcher>, ExprError> {
+        if *self.peek() == Token::Not {
+            let depth = self.nest(depth)?;
+            self.advance();
+            let inner = self.parse_unary(depth)?;
+            return Ok(Arc::new(NotMatcher::new(inner)));
+        }
+        self.parse_primary(depth)
+    }
+
+    /// 进入下一层嵌套，超过 `MAX_EXPR_DEPTH` 时报错（避免恶意配置撑爆调用栈）。
+    fn nest(&self, depth: usize) -> Result<usize, ExprError> {
+        if depth >= MAX_EXPR_DEPTH {
+            return Err(self.error(format!(
+                "expression nests deeper than {MAX_EXPR_DEPTH} levels"
+            )));
+        }
+        Ok(depth + 1)
+    }
+
+    fn parse_primary(&mut self, depth: usize) -> Result<Arc<dyn Matcher>, ExprError> {
+        if *self.peek() == Token::LParen {
+            self.nest(depth)?;
+        }
+        let (pos, token) = self.advance();
+        match token {
+            Token::LParen => {
+                let inner = self.parse_or(depth + 1)?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Arc::new(AlwaysMatcher)),
+                "false" => Ok(Arc::new(NotMatcher::new(Arc::new(AlwaysMatcher)))),
+                "name" | "path" if *self.peek() != Token::LParen => self.parse_compare(&ident),
+                _ => self.parse_call(pos, &ident),
+            },
+            other => Err(ExprError::new(
+                pos,
+                format!("expected expression, found {}", other.describe()),
+            )),
+        }
+    }
+
+    fn parse_compare(&mut self, field: &str) -> Result<Arc<dyn Matcher>, ExprError> {
+        let (op_pos, op) = self.advance();
+        let (value_pos, value) = self.advance();
+        let Token::Str(value) = value else {
+            return Err(ExprError::new(
+                value_pos,
+                format!(
+                    "expected string after `{field}`, found {}",
+                    value.describe()
+                ),
+            ));
+        };
+
+        let matcher: Arc<dyn Matcher> = match (field, &op) {
+            ("name", Token::Tilde) => Arc::new(NameGlobMatcher::new(value)),
+            ("path", Token::Tilde) => Arc::new(
+                FnMatcher::new(format!("path({value:?})"), move |ctx: &MatchContext| {
+                    glob_match(&value, &ctx.route.path)
+                })
+                .route_only(),
+            ),
+            ("name", Token::EqEq | Token::NotEq) => Arc::new(
+                FnMatcher::new(format!("name_eq({value:?})"), move |ctx: &MatchContext| {
+                    ctx.route.name == value
+                })
+                .route_only(),
+            ),
+            ("path", Token::EqEq | Token::NotEq) => Arc::new(
+                FnMatcher::new(format!("path_eq({value:?})"), move |ctx: &MatchContext| {
+                    ctx.route.path == value
+                })
+                .route_only(),
+            ),
+            _ => {
+                return Err(ExprError::new(
+                    op_pos,
+                    format!(
+                        "expected `~`, `==` or `!=` after `{field}`, found {}",
+                        op.describe()
+                    ),
+                ));
+            }
+        };
+
+        Ok(if op == Token::NotEq {
+            Arc::new(NotMatcher::new(matcher))
+        } else {
+            matcher
+        })
+    }
+
+    fn parse_call(&mut self, pos: usize, func: &str) -> Result<Arc<dyn Matcher>, ExprError> {
+        self.expect(Token::LParen)?;
+        let mut args = Vec::new();
+        if *self.peek() != Token::RParen {
+            loop {
+                args.push(self.advance());
+                if *self.peek() == Token::Comma {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(Token::RParen)?;
+
+        let arity = |expected: &str, ok: bool| -> Result<(), ExprError> {
+            if ok {
+                Ok(())
+            } else {
+                Err(ExprError::new(
+                    pos,
+                    format!("`{func}` expects {expected}, got {}", args.len()),
+                ))
+            }
+        };
+
+        match func {
+            "tag" => {
+                arity("1 argument", args.len() == 1)?;
+                Ok(Arc::new(TagMatcher::new(string_arg(&args[0])?)))
+            }
+            "all_tags" | "any_tags" => {
+                arity("at least 1 argument", !args.is_empty())?;
+                let tags = args.iter().map(string_arg).collect::<Result<Vec<_>, _>>()?;
+                Ok(if func == "all_tags" {
+                    Arc::new(AllTagsMatcher::new(tags))
+                } else {
+                    Arc::new(AnyTagsMatcher::new(tags))
+                })
+            }
+            "runtime" => {
+                arity("1 argument", args.len() == 1)?;
+                Ok(Arc::new(RuntimeMatcher::new(runtime_arg(&args[0])?)))
+            }
+            "stack" => {
+                arity("1 argument", args.len() == 1)?;
+                Ok(Arc::new(StackMatcher::new(StackId(string_arg(&args[0])?))))
+            }
+            "path_prefix" => {
+                arity("1 argument", args.len() == 1)?;
+                Ok(Arc::new(PathPrefixMatcher::new(string_arg(&args[0])?)))
+            }
+            "param" => {
+                arity("1 or 2 arguments", matches!(args.len(), 1 | 2))?;
+                let key = string_arg(&args[0])?;
+                Ok(match args.get(1) {
+                    Some(value) => Arc::new(ParamMatcher::equals(key, literal_arg(value)?)),
+                    None => Arc::new(ParamMatcher::present(key)),
+                })
+            }
+            _ => Err(ExprError::new(pos, format!("unknown function `{func}`"))),
+        }
+    }
+}
+
+fn string_arg((pos, token): &(usize, Token)) -> Result<String, ExprError> {
+    match token {
+        Token::Str(s) => Ok(s.clone()),
+        other => Err(ExprError::new(
+            *pos,
+            format!("expected string argument, found {}", other.describe()),
+        )),
+    }
+}
+
+fn runtime_arg((pos, token): &(usize, Token)) -> Result<RuntimeKind, ExprError> {
+    let name = match token {
+        Token::Ident(name) | Token::Str(name) => name.as_str(),
+        other => {
+            return Err(ExprError::new(
+                *pos,
+                format!("expected runtime name, found {}", other.describe()),
+            ));
+        }
+    };
+    match name {
+        "Native" => Ok(RuntimeKind::Native),
+        "ReactNative" => Ok(RuntimeKind::ReactNative),
+        "Flutter" => Ok(RuntimeKind::Flutter),
+        other => Err(ExprError::new(*pos, format!("unknown runtime `{other}`"))),
+    }
+}
+
+fn literal_arg((pos, token): &(usize, Token)) -> Result<Value, ExprError> {
+    match token {
+        Token::Str(s) => Ok(Value::String(s.clone())),
+        Token::Number(n) => Ok(Value::Number(n.clone())),
+        Token::Ident(ident) if ident == "true" => Ok(Value::Bool(true)),
+        Token::Ident(ident) if ident == "false" => Ok(Value::Bool(false)),
+        Token::Ident(ident) if ident == "null" => Ok(Value::Null),
+        other => Err(ExprError::new(
+            *pos,
+            format!("expected literal, found {}", other.describe()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::route::RouteMeta;
+    use crate::umrouter_core::test_support::route;
+    use crate::umrouter_core::types::CanonicalParams;
+
+    fn eval(source: &str, route: &RouteMeta, runtime: RuntimeKind) -> bool {
+        let mut params = CanonicalParams::default();
+        params.map.insert("orderId".into(), Value::from(42));
+        let ctx = MatchContext {
+            route,
+            target_stack: &route.preferred_stack,
+            runtime,
+            params: &params,
+        };
+        ExprMatcher::compile(source).unwrap().matches(&ctx)
+    }
+
+    #[test]
+    fn evaluates_boolean_expressions() {
+        let src = r#"tag("auth-required") && !runtime(Flutter) && name ~ "orders.*""#;
+        let orders = route(
+            "orders.detail",
+            "/orders/:orderId/detail",
+            &["auth-required"],
+        );
+        assert!(eval(src, &orders, RuntimeKind::Native));
+        assert!(!eval(src, &orders, RuntimeKind::Flutter));
+        assert!(!eval(
+            src,
+            &route("home.index", "/home", &["auth-required"]),
+            RuntimeKind::Native
+        ));
+
+        assert!(eval(
+            r#"param("orderId", 42) || false"#,
+            &orders,
+            RuntimeKind::Native
+        ));
+        assert!(eval(
+            r#"!(stack("home") || path == "/home")"#,
+            &orders,
+            RuntimeKind::Native
+        ));
+        assert!(eval(
+            r#"any_tags("x", "auth-required")"#,
+            &orders,
+            RuntimeKind::Native
+        ));
+    }
+
+    #[test]
+    fn name_is_source_text() {
+        let src = r#"tag("a") || tag("b")"#;
+        assert_eq!(ExprMatcher::compile(src).unwrap().name(), src);
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let src = format!("{}true", "!".repeat(200_000));
+        let err = ExprMatcher::compile(src).unwrap_err();
+        assert_eq!(err.position, MAX_EXPR_DEPTH);
+
+        let src = format!("{}true{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(ExprMatcher::compile(src).is_err());
+
+        let src = format!("{}true", "!".repeat(MAX_EXPR_DEPTH));
+        assert!(ExprMatcher::compile(src).is_ok());
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let err = ExprMatcher::compile(r#"tag("a") && runtime(Swift)"#).unwrap_err();
+        assert_eq!(err.position, 20);
+
+        let err = ExprMatcher::compile(r#"tag("a" ||"#).unwrap_err();
+        assert_eq!(err.position, 8);
+
+        let err = ExprMatcher::compile(r#"name = "x""#).unwrap_err();
+        assert_eq!(err.position, 5);
+
+        let err = ExprMatcher::compile(r#"tag("unterminated)"#).unwrap_err();
+        assert_eq!(err.position, 4);
+    }
+
+    #[test]
+    fn cache_reuses_compiled_expressions() {
+        let cache = ExprCache::new();
+        let a = cache.get_or_compile(r#"tag("a")"#).unwrap();
+        let b = cache.get_or_compile(r#"tag("a")"#).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(cache.get_or_compile("tag(").is_err());
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::types::{MatchContext, Middleware};
+use crate::umrouter_core::types::MiddlewareId;
+
+/// 中间件出错（panic）时的处理策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FailurePolicy {
+    /// fail-open：跳过该中间件，导航继续。
+    #[default]
+    FailOpen,
+
+    /// fail-closed：中止整个导航。
+    FailClosed,
+}
+
+/// 出错发生在中间件的哪个部分。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureStage {
+    /// matcher 执行时 panic。
+    Matcher,
+
+    /// executor 执行时 panic。
+    Executor,
+}
+
+/// 中间件执行失败的描述。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiddlewareFailure {
+    /// 出错的中间件。
+    pub id: MiddlewareId,
+
+    /// 出错位置。
+    pub stage: FailureStage,
+
+    /// panic 信息。
+    pub message: String,
+
+    /// 该中间件声明的失败策略。
+    pub policy: FailurePolicy,
+}
+
+impl MiddlewareFailure {
+    /// 是否需要中止导航。
+    pub fn is_blocking(&self) -> bool {
+        self.policy == FailurePolicy::FailClosed
+    }
+}
+
+impl fmt::Display for MiddlewareFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            FailureStage::Matcher => "matcher",
+            FailureStage::Executor => "executor",
+        };
+        write!(
+            f,
+            "{} of middleware `{}` panicked: {}",
+            stage, self.id.0, self.message
+        )
+    }
+}
+
+impl std::error::Error for MiddlewareFailure {}
+
+/// 执行 matcher，并把 panic 转换为 MiddlewareFailure。
+pub(crate) fn catch_matcher(
+    middleware: &Middleware,
+    ctx: &MatchContext,
+) -> Result<bool, MiddlewareFailure> {
+    panic::catch_unwind(AssertUnwindSafe(|| middleware.matcher.matches(ctx)))
+        .map_err(|payload| failure(middleware, FailureStage::Matcher, payload))
+}
+
+/// 根据 panic payload 构造 MiddlewareFailure。
+pub(crate) fn failure(
+    middleware: &Middleware,
+    stage: FailureStage,
+    payload: Box<dyn Any + Send>,
+) -> MiddlewareFailure {
+    MiddlewareFailure {
+        id: middleware.id.clone(),
+        stage,
+        message: panic_message(payload.as_ref()),
+        policy: middleware.failure_policy,
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_owned()
+    }
+}
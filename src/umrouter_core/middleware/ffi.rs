@@ -0,0 +1,389 @@
+//! C ABI：宿主语言（Swift / Kotlin 等）通过函数指针表实现 Matcher / Executor。
+//!
+//! 上下文与执行结果以 JSON 字符串传递，格式见 `json` 模块；
+//! C 侧的声明见 `include/umrouter.h`。
+//!
+//! 所有权与线程约定：
+//! - `user_data` 归适配器所有，适配器析构时调用一次 `drop`（可为空），之后不再使用
+//! - 回调可能在任意线程、并发地调用，宿主需保证 `user_data` 线程安全
+//! - 传入的 `context_json` 只在本次调用期间有效，宿主需要时应自行拷贝
+//! - `execute` 返回的字符串由宿主分配，Rust 拷贝后立即通过 `free_result` 归还；
+//!   返回 NULL 视为 Continue
+//! - 回调不得向 Rust 抛出异常 / 展开栈
+
+use std::ffi::{CStr, CString, c_char, c_void};
+
+use super::json;
+use super::types::{
+    ExecuteContext, Executor, MatchContext, Matcher, MatcherDependency, MiddlewareResult,
+};
+
+/// 宿主实现的 matcher 函数指针表。
+#[repr(C)]
+#[derive(Debug)]
+pub struct UmMatcherVTable {
+    /// 宿主的不透明数据，原样传回每个回调。
+    pub user_data: *mut c_void,
+
+    /// 判断是否匹配（不可为空）；`context_json` 为 NUL 结尾的 UTF-8 匹配上下文。
+    pub matches: extern "C" fn(user_data: *mut c_void, context_json: *const c_char) -> bool,
+
+    /// 释放 `user_data`，可为空。
+    pub drop: Option<extern "C" fn(user_data: *mut c_void)>,
+
+    /// 调试名称（NUL 结尾，构造时拷贝），可为空。
+    pub name: *const c_char,
+}
+
+/// 宿主实现的 executor 函数指针表。
+#[repr(C)]
+#[derive(Debug)]
+pub struct UmExecutorVTable {
+    /// 宿主的不透明数据，原样传回每个回调。
+    pub user_data: *mut c_void,
+
+    /// 执行中间件（不可为空），返回宿主分配的 NUL 结尾 JSON 执行结果；返回 NULL 视为 Continue。
+    pub execute: extern "C" fn(user_data: *mut c_void, context_json: *const c_char) -> *mut c_char,
+
+    /// 释放 `execute` 返回的字符串，可为空（字符串为静态数据时）。
+    pub free_result: Option<extern "C" fn(user_data: *mut c_void, result: *mut c_char)>,
+
+    /// 释放 `user_data`，可为空。
+    pub drop: Option<extern "C" fn(user_data: *mut c_void)>,
+
+    /// 调试名称（NUL 结尾，构造时拷贝），可为空。
+    pub name: *const c_char,
+}
+
+/// 将 `UmMatcherVTable` 适配为 `Matcher`。
+#[derive(Debug)]
+pub struct FfiMatcher {
+    vtable: UmMatcherVTable,
+    name: String,
+    dependency: MatcherDependency,
+}
+
+// SAFETY: `FfiMatcher::new` 的调用方保证 user_data 与回调可以跨线程并发使用。
+unsafe impl Send for FfiMatcher {}
+unsafe impl Sync for FfiMatcher {}
+
+impl FfiMatcher {
+    /// 接管函数指针表。
+    ///
+    /// # Safety
+    ///
+    /// - 函数指针在适配器存活期间有效，`name` 为空或指向 NUL 结尾字符串
+    /// - `user_data` 与回调满足模块文档中的所有权与线程约定
+    pub unsafe fn new(vtable: UmMatcherVTable) -> Self {
+        // SAFETY: 由调用方保证 name 有效。
+        let name = unsafe { vtable_name(vtable.name, "ffi_matcher") };
+        Self {
+            vtable,
+            name,
+            dependency: MatcherDependency::Request,
+        }
+    }
+
+    /// 声明匹配结果的数据依赖；只读取 `route` 的宿主 matcher 应设为 `RouteOnly`。
+    pub fn with_dependency(mut self, dependency: MatcherDependency) -> Self {
+        self.dependency = dependency;
+        self
+    }
+}
+
+impl Matcher for FfiMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        let input = context_string(json::match_context(ctx));
+        (self.vtable.matches)(self.vtable.user_data, input.as_ptr())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        self.dependency
+    }
+}
+
+impl Drop for FfiMatcher {
+    fn drop(&mut self) {
+        if let Some(drop) = self.vtable.drop {
+            drop(self.vtable.user_data);
+        }
+    }
+}
+
+/// 将 `UmExecutorVTable` 适配为 `Executor`。
+///
+/// 宿主返回无法解析的结果时视为中间件 panic，按该中间件的 FailurePolicy 处理。
+#[derive(Debug)]
+pub struct FfiExecutor {
+    vtable: UmExecutorVTable,
+    name: String,
+}
+
+// SAFETY: `FfiExecutor::new` 的调用方保证 user_data 与回调可以跨线程并发使用。
+unsafe impl Send for FfiExecutor {}
+unsafe impl Sync for FfiExecutor {}
+
+impl FfiExecutor {
+    /// 接管函数指针表。
+    ///
+    /// # Safety
+    ///
+    /// - 函数指针在适配器存活期间有效，`name` 为空或指向 NUL 结尾字符串
+    /// - `execute` 返回 NULL 或有效的 NUL 结尾字符串
+    /// - `user_data` 与回调满足模块文档中的所有权与线程约定
+    pub unsafe fn new(vtable: UmExecutorVTable) -> Self {
+        // SAFETY: 由调用方保证 name 有效。
+        let name = unsafe { vtable_name(vtable.name, "ffi_executor") };
+        Self { vtable, name }
+    }
+}
+
+impl Executor for FfiExecutor {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        let input = context_string(json::execute_context(ctx));
+        let raw = (self.vtable.execute)(self.vtable.user_data, input.as_ptr());
+        if raw.is_null() {
+            return MiddlewareResult::Continue;
+        }
+        // SAFETY: 构造时约定 execute 返回有效的 NUL 结尾字符串。
+        let output = unsafe { CStr::from_ptr(raw) }
+            .to_string_lossy()
+            .into_owned();
+        if let Some(free) = self.vtable.free_result {
+            free(self.vtable.user_data, raw);
+        }
+        json::apply_execute_output(ctx, &output)
+            .unwrap_or_else(|e| panic!("{} returned an invalid result: {e}", self.name))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for FfiExecutor {
+    fn drop(&mut self) {
+        if let Some(drop) = self.vtable.drop {
+            drop(self.vtable.user_data);
+        }
+    }
+}
+
+/// serde_json 会转义 NUL，序列化结果不会包含内部 NUL。
+fn context_string(value: serde_json::Value) -> CString {
+    CString::new(value.to_string()).expect("JSON never contains NUL")
+}
+
+/// # Safety
+///
+/// `name` 为空或指向 NUL 结尾字符串。
+unsafe fn vtable_name(name: *const c_char, fallback: &str) -> String {
+    if name.is_null() {
+        return fallback.into();
+    }
+    // SAFETY: 由调用方保证。
+    unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "ffi-fixture")]
+    use std::ffi::c_int;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::umrouter_core::middleware::{
+        Middleware, MiddlewareExecutor, MiddlewarePhase, MiddlewareRegistry,
+    };
+    use crate::umrouter_core::pipeline::{NavTarget, PipelineResult};
+    use crate::umrouter_core::route::RouteMeta;
+    use crate::umrouter_core::test_support::{route, run_pre_rw};
+    use crate::umrouter_core::types::{CanonicalParams, MiddlewareId};
+
+    fn vip_registry(matcher: FfiMatcher, executor: FfiExecutor) -> MiddlewareRegistry {
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(Middleware::new(
+                MiddlewareId("vip_guard".into()),
+                Arc::new(matcher),
+                MiddlewareExecutor::sync(executor),
+                MiddlewarePhase::PreRW,
+            ))
+            .unwrap();
+        registry
+    }
+
+    /// 以会员等级导航，返回执行结果与 VIP 守卫写入的检查结果。
+    fn run(
+        registry: &MiddlewareRegistry,
+        meta: &RouteMeta,
+        member: &str,
+    ) -> (PipelineResult, Option<String>) {
+        let mut params = CanonicalParams::default();
+        params.map.insert("member".into(), member.into());
+        let (result, extensions) = run_pre_rw(registry, meta, params);
+        (result, extensions.get_str("vip.checked").map(str::to_owned))
+    }
+
+    /// 以 Rust 回调模拟的宿主，记录回调与释放次数。
+    #[derive(Default)]
+    struct Host {
+        executed: AtomicUsize,
+        freed: AtomicUsize,
+        dropped: AtomicUsize,
+    }
+
+    /// 把一份 Host 引用交给适配器，由 `host_drop` 归还。
+    fn host_data(host: &Arc<Host>) -> *mut c_void {
+        Arc::into_raw(Arc::clone(host)) as *mut c_void
+    }
+
+    fn host_context(context_json: *const c_char) -> serde_json::Value {
+        // SAFETY: 适配器传入本次调用期间有效的 NUL 结尾字符串。
+        let text = unsafe { CStr::from_ptr(context_json) }.to_str().unwrap();
+        serde_json::from_str(text).unwrap()
+    }
+
+    extern "C" fn host_matches(_user_data: *mut c_void, context_json: *const c_char) -> bool {
+        host_context(context_json)["route"]["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|tag| tag == "vip")
+    }
+
+    extern "C" fn host_execute(user_data: *mut c_void, context_json: *const c_char) -> *mut c_char {
+        // SAFETY: user_data 来自 host_data，在 host_drop 之前一直有效。
+        let host = unsafe { &*(user_data as *const Host) };
+        host.executed.fetch_add(1, Ordering::SeqCst);
+        let result = match host_context(context_json)["params"]["member"].as_str() {
+            Some("gold") => r#"{"result":"continue","extensions":{"vip.checked":"gold"}}"#,
+            Some("staff") => return std::ptr::null_mut(),
+            _ => r#"{"result":"redirect","request":{"name":"vip.upgrade"}}"#,
+        };
+        CString::new(result).unwrap().into_raw()
+    }
+
+    extern "C" fn host_free_result(user_data: *mut c_void, result: *mut c_char) {
+        // SAFETY: user_data 来自 host_data；result 来自 host_execute 的 CString::into_raw。
+        let host = unsafe { &*(user_data as *const Host) };
+        drop(unsafe { CString::from_raw(result) });
+        host.freed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    extern "C" fn host_drop(user_data: *mut c_void) {
+        // SAFETY: user_data 来自 host_data，每个适配器只归还一次。
+        let host = unsafe { Arc::from_raw(user_data as *const Host) };
+        host.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn adapters_own_host_vtables() {
+        let host = Arc::new(Host::default());
+        let name = CString::new("host_guard").unwrap();
+        // SAFETY: 回调只访问 host_data 交出的 Host，name 在构造期间有效。
+        let (matcher, executor) = unsafe {
+            (
+                FfiMatcher::new(UmMatcherVTable {
+                    user_data: host_data(&host),
+                    matches: host_matches,
+                    drop: Some(host_drop),
+                    name: std::ptr::null(),
+                }),
+                FfiExecutor::new(UmExecutorVTable {
+                    user_data: host_data(&host),
+                    execute: host_execute,
+                    free_result: Some(host_free_result),
+                    drop: Some(host_drop),
+                    name: name.as_ptr(),
+                }),
+            )
+        };
+        // 名称在构造时拷贝，不再引用宿主内存。
+        drop(name);
+        assert_eq!(matcher.name(), "ffi_matcher");
+        assert_eq!(executor.name(), "host_guard");
+
+        let registry = vip_registry(matcher, executor);
+        let lounge = route("vip.lounge", "/vip/lounge", &["vip"]);
+        let (result, checked) = run(&registry, &lounge, "gold");
+        assert!(matches!(result, PipelineResult::Completed));
+        assert_eq!(checked.as_deref(), Some("gold"));
+
+        let PipelineResult::Redirected { request, .. } = run(&registry, &lounge, "silver").0 else {
+            panic!("expected redirect to upgrade page");
+        };
+        assert_eq!(request.target, NavTarget::Name("vip.upgrade".into()));
+
+        // 返回 NULL 视为 Continue，也不会调用 free_result。
+        let (result, checked) = run(&registry, &lounge, "staff");
+        assert!(matches!(result, PipelineResult::Completed));
+        assert_eq!(checked, None);
+
+        // 没有 vip 标签的路由不会执行宿主 executor。
+        let home = route("home.index", "/home", &[]);
+        assert!(matches!(
+            run(&registry, &home, "silver").0,
+            PipelineResult::Completed
+        ));
+
+        // 每个宿主分配的结果都被归还一次；user_data 在适配器析构时各释放一次。
+        assert_eq!(host.executed.load(Ordering::SeqCst), 3);
+        assert_eq!(host.freed.load(Ordering::SeqCst), 2);
+        assert_eq!(host.dropped.load(Ordering::SeqCst), 0);
+        drop(registry);
+        assert_eq!(host.dropped.load(Ordering::SeqCst), 2);
+        assert_eq!(Arc::strong_count(&host), 1);
+    }
+
+    // 由 build.rs 编译的 C 实现：tests/ffi/vip_guard.c。
+    #[cfg(feature = "ffi-fixture")]
+    #[link(name = "umrouter_ffi_fixture", kind = "static")]
+    unsafe extern "C" {
+        fn um_fixture_vip_matcher(drops: *mut c_int) -> UmMatcherVTable;
+        fn um_fixture_vip_executor(drops: *mut c_int) -> UmExecutorVTable;
+    }
+
+    #[cfg(feature = "ffi-fixture")]
+    #[test]
+    fn runs_guard_implemented_in_c() {
+        let mut matcher_drops: c_int = 0;
+        let mut executor_drops: c_int = 0;
+        // SAFETY: C fixture 的回调无状态，drop 只递增测试持有的计数。
+        let (matcher, executor) = unsafe {
+            (
+                FfiMatcher::new(um_fixture_vip_matcher(&mut matcher_drops)),
+                FfiExecutor::new(um_fixture_vip_executor(&mut executor_drops)),
+            )
+        };
+        assert_eq!(executor.name(), "vip_guard");
+        let registry = vip_registry(matcher, executor);
+
+        let lounge = route("vip.lounge", "/vip/lounge", &["vip"]);
+        let (result, checked) = run(&registry, &lounge, "gold");
+        assert!(matches!(result, PipelineResult::Completed));
+        assert_eq!(checked.as_deref(), Some("gold"));
+
+        let (result, _) = run(&registry, &lounge, "silver");
+        let PipelineResult::Redirected { request, .. } = result else {
+            panic!("expected redirect to upgrade page");
+        };
+        assert_eq!(request.target, NavTarget::Name("vip.upgrade".into()));
+
+        // 没有 vip 标签的路由不会执行 C executor。
+        let home = route("home.index", "/home", &[]);
+        let (result, checked) = run(&registry, &home, "silver");
+        assert!(matches!(result, PipelineResult::Completed));
+        assert_eq!(checked, None);
+
+        drop(registry);
+        assert_eq!((matcher_drops, executor_drops), (1, 1));
+    }
+}
@@ -0,0 +1,213 @@
+//! 跨语言中间件（FFI / WASM）共用的 JSON 协议。
+//!
+//! 匹配上下文：
+//!
+//! ```json
+//! {
+//!   "route": { "id": 0, "name": "orders.detail", "path": "/orders/:id",
+//!              "tags": ["auth-required"], "runtime": "native", "preferred_stack": "main" },
+//!   "target_stack": "main",
+//!   "runtime": "native",
+//!   "params": { "id": "42" }
+//! }
+//! ```
+//!
+//! 执行上下文在匹配上下文基础上增加 `"presentation"`、`"animation"` 与
+//! `"extensions"`（Extensions 的字符串表）。
+//!
+//! 执行结果：
+//!
+//! ```json
+//! { "result": "continue" }
+//! { "result": "abort", "reason": "..." }
+//! { "result": "redirect", "request": { "name": "auth.login", "params": {}, "target_stack": "auth", "presentation": "modal" } }
+//! { "result": "rewrite", "rewrite": { "target_stack": "tablet", "presentation": "sheet" } }
+//! ```
+//!
+//! 结果中可选的 `"params"`（整体替换参数）与 `"extensions"`（合并进字符串表）
+//! 会在 ReadWrite 模式下写回。
+//!
+//! runtime 取值：native / react_native / flutter；
+//! presentation 取值：push / modal / sheet / replace / custom。
+
+use serde_json::{Map, Value, json};
+
+use super::types::{ExecuteContext, MatchContext, MiddlewareResult, NavRewrite};
+use crate::umrouter_core::pipeline::{NavRequest, NavTarget};
+use crate::umrouter_core::types::{CanonicalParams, PresentationMode, RuntimeKind, StackId};
+
+/// 序列化匹配上下文。
+pub(crate) fn match_context(ctx: &MatchContext) -> Value {
+    let route = ctx.route;
+    json!({
+        "route": {
+            "id": route.id.0,
+            "name": route.name,
+            "path": route.path,
+            "tags": route.tags,
+            "runtime": runtime_name(route.runtime),
+            "preferred_stack": route.preferred_stack.0,
+        },
+        "target_stack": ctx.target_stack.0,
+        "runtime": runtime_name(ctx.runtime),
+        "params": params_object(ctx.params),
+    })
+}
+
+/// 序列化执行上下文。
+pub(crate) fn execute_context(ctx: &ExecuteContext) -> Value {
+    let mut value = match_context(&ctx.match_context());
+    let extensions: Map<String, Value> = ctx
+        .extensions
+        .str_entries()
+        .map(|(k, v)| (k.to_owned(), Value::from(v)))
+        .collect();
+    let object = value.as_object_mut().expect("context is an object");
+    object.insert(
+        "presentation".into(),
+        presentation_name(ctx.transition.presentation).into(),
+    );
+    object.insert("animation".into(), json!(ctx.transition.animation));
+    object.insert("extensions".into(), Value::Object(extensions));
+    value
+}
+
+/// 解析执行结果，并把其中的参数 / 扩展数据修改写回执行上下文。
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+pub(crate) fn apply_execute_output(
+    ctx: &mut ExecuteContext,
+    output: &str,
+) -> Result<MiddlewareResult, String> {
+    let value: Value = serde_json::from_str(output).map_err(|e| format!("invalid JSON: {e}"))?;
+    apply_execute_value(ctx, &value)
+}
+
+/// 同 `apply_execute_output`，输入为已解析的 JSON。
+pub(crate) fn apply_execute_value(
+    ctx: &mut ExecuteContext,
+    value: &Value,
+) -> Result<MiddlewareResult, String> {
+    let object = value.as_object().ok_or("result must be an object")?;
+
+    let result = match str_field(object, "result")?.unwrap_or("continue") {
+        "continue" => MiddlewareResult::Continue,
+        "abort" => MiddlewareResult::Abort {
+            reason: str_field(object, "reason")?.unwrap_or_default().to_owned(),
+        },
+        "redirect" => {
+            let request = object
+                .get("request")
+                .and_then(Value::as_object)
+                .ok_or("redirect requires a `request` object")?;
+            MiddlewareResult::redirect(parse_request(request)?)
+        }
+        "rewrite" => {
+            let rewrite = object
+                .get("rewrite")
+                .and_then(Value::as_object)
+                .ok_or("rewrite requires a `rewrite` object")?;
+            MiddlewareResult::Rewrite(parse_rewrite(rewrite)?)
+        }
+        other => return Err(format!("unknown result `{other}`")),
+    };
+
+    if let Some(params) = object.get("params") {
+        *ctx.params = parse_params(params)?;
+    }
+    if let Some(extensions) = object.get("extensions") {
+        let extensions = extensions
+            .as_object()
+            .ok_or("`extensions` must be an object")?;
+        for (key, value) in extensions {
+            let value = value
+                .as_str()
+                .ok_or_else(|| format!("extension `{key}` must be a string"))?;
+            ctx.extensions.insert_str(key.clone(), value);
+        }
+    }
+    Ok(result)
+}
+
+fn parse_request(object: &Map<String, Value>) -> Result<NavRequest, String> {
+    let target = match (str_field(object, "name")?, str_field(object, "path")?) {
+        (Some(name), _) => NavTarget::Name(name.to_owned()),
+        (None, Some(path)) => NavTarget::Path(path.to_owned()),
+        (None, None) => return Err("request requires `name` or `path`".into()),
+    };
+    let mut request = NavRequest::new(target);
+    if let Some(params) = object.get("params") {
+        request.params = parse_params(params)?;
+    }
+    if let Some(stack) = str_field(object, "target_stack")? {
+        request.target_stack = Some(StackId(stack.to_owned()));
+    }
+    if let Some(presentation) = str_field(object, "presentation")? {
+        request.presentation = Some(parse_presentation(presentation)?);
+    }
+    Ok(request)
+}
+
+fn parse_rewrite(object: &Map<String, Value>) -> Result<NavRewrite, String> {
+    let mut rewrite = NavRewrite::new();
+    if let Some(stack) = str_field(object, "target_stack")? {
+        rewrite.target_stack = Some(StackId(stack.to_owned()));
+    }
+    if let Some(presentation) = str_field(object, "presentation")? {
+        rewrite.presentation = Some(parse_presentation(presentation)?);
+    }
+    Ok(rewrite)
+}
+
+fn parse_params(value: &Value) -> Result<CanonicalParams, String> {
+    let object = value.as_object().ok_or("`params` must be an object")?;
+    Ok(CanonicalParams {
+        map: object.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+    })
+}
+
+fn params_object(params: &CanonicalParams) -> Value {
+    Value::Object(
+        params
+            .map
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+    )
+}
+
+fn str_field<'v>(object: &'v Map<String, Value>, key: &str) -> Result<Option<&'v str>, String> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(format!("`{key}` must be a string")),
+    }
+}
+
+pub(crate) fn runtime_name(runtime: RuntimeKind) -> &'static str {
+    match runtime {
+        RuntimeKind::Native => "native",
+        RuntimeKind::ReactNative => "react_native",
+        RuntimeKind::Flutter => "flutter",
+    }
+}
+
+pub(crate) fn presentation_name(presentation: PresentationMode) -> &'static str {
+    match presentation {
+        PresentationMode::Push => "push",
+        PresentationMode::Modal => "modal",
+        PresentationMode::Sheet => "sheet",
+        PresentationMode::Replace => "replace",
+        PresentationMode::Custom => "custom",
+    }
+}
+
+fn parse_presentation(name: &str) -> Result<PresentationMode, String> {
+    Ok(match name {
+        "push" => PresentationMode::Push,
+        "modal" => PresentationMode::Modal,
+        "sheet" => PresentationMode::Sheet,
+        "replace" => PresentationMode::Replace,
+        "custom" => PresentationMode::Custom,
+        other => return Err(format!("unknown presentation `{other}`")),
+    })
+}
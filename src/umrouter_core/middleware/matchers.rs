@@ -0,0 +1,559 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use super::types::{MatchContext, Matcher, MatcherDependency};
+use crate::umrouter_core::types::{RuntimeKind, StackId};
+
+//
+// ========== 内建 Matcher ==========
+//
+
+/// 路由带有指定标签时匹配。
+pub struct TagMatcher {
+    tag: String,
+    name: String,
+}
+
+impl TagMatcher {
+    pub fn new(tag: impl Into<String>) -> Self {
+        let tag = tag.into();
+        Self {
+            name: format!("tag({tag:?})"),
+            tag,
+        }
+    }
+}
+
+impl Matcher for TagMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        ctx.route.tags.contains(&self.tag)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
+}
+
+/// 路由同时带有所有指定标签时匹配。
+pub struct AllTagsMatcher {
+    tags: Vec<String>,
+    name: String,
+}
+
+impl AllTagsMatcher {
+    pub fn new<I, S>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+        Self {
+            name: format!("all_tags({})", quoted_list(&tags)),
+            tags,
+        }
+    }
+}
+
+impl Matcher for AllTagsMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        self.tags.iter().all(|tag| ctx.route.tags.contains(tag))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
+}
+
+/// 路由带有任意一个指定标签时匹配。
+pub struct AnyTagsMatcher {
+    tags: Vec<String>,
+    name: String,
+}
+
+impl AnyTagsMatcher {
+    pub fn new<I, S>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+        Self {
+            name: format!("any_tags({})", quoted_list(&tags)),
+            tags,
+        }
+    }
+}
+
+impl Matcher for AnyTagsMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        self.tags.iter().any(|tag| ctx.route.tags.contains(tag))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
+}
+
+/// 路由名匹配 glob 模式时匹配。
+///
+/// `*` 匹配任意长度字符，`?` 匹配单个字符，
+/// 例如 "orders.*" 匹配 "orders.list" / "orders.detail.items"。
+pub struct NameGlobMatcher {
+    pattern: String,
+    name: String,
+}
+
+impl NameGlobMatcher {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        Self {
+            name: format!("name({pattern:?})"),
+            pattern,
+        }
+    }
+}
+
+impl Matcher for NameGlobMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        glob_match(&self.pattern, &ctx.route.name)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
+}
+
+/// 路由路径模式以指定前缀开头时匹配。
+///
+/// 按路径段比较："/account" 匹配 "/account" 与 "/account/settings"，
+/// 但不匹配 "/accounts"。
+pub struct PathPrefixMatcher {
+    prefix: String,
+    name: String,
+}
+
+impl PathPrefixMatcher {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        Self {
+            name: format!("path_prefix({prefix:?})"),
+            prefix,
+        }
+    }
+}
+
+impl Matcher for PathPrefixMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        path_has_prefix(&ctx.route.path, &self.prefix)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
+}
+
+/// 当前请求的 runtime 等于指定值时匹配。
+pub struct RuntimeMatcher {
+    runtime: RuntimeKind,
+    name: String,
+}
+
+impl RuntimeMatcher {
+    pub fn new(runtime: RuntimeKind) -> Self {
+        Self {
+            runtime,
+            name: format!("runtime({runtime:?})"),
+        }
+    }
+}
+
+impl Matcher for RuntimeMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        ctx.runtime == self.runtime
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// 目标栈等于指定栈时匹配。
+pub struct StackMatcher {
+    stack: StackId,
+    name: String,
+}
+
+impl StackMatcher {
+    pub fn new(stack: StackId) -> Self {
+        Self {
+            name: format!("stack({:?})", stack.0),
+            stack,
+        }
+    }
+}
+
+impl Matcher for StackMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        *ctx.target_stack == self.stack
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// 参数匹配：
+/// - 只指定 key 时，参数存在即匹配
+/// - 同时指定 value 时，参数值相等才匹配
+pub struct ParamMatcher {
+    key: String,
+    value: Option<Value>,
+    name: String,
+}
+
+impl ParamMatcher {
+    /// 参数 key 存在时匹配。
+    pub fn present(key: impl Into<String>) -> Self {
+        let key = key.into();
+        Self {
+            name: format!("param({key:?})"),
+            key,
+            value: None,
+        }
+    }
+
+    /// 参数 key 的值等于 value 时匹配。
+    pub fn equals(key: impl Into<String>, value: impl Into<Value>) -> Self {
+        let key = key.into();
+        let value = value.into();
+        Self {
+            name: format!("param({key:?}, {value})"),
+            key,
+            value: Some(value),
+        }
+    }
+}
+
+impl Matcher for ParamMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        match (ctx.params.map.get(&self.key), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+//
+// ========== 组合子 ==========
+//
+
+/// 所有子 matcher 都匹配时匹配（短路求值）。
+pub struct AndMatcher {
+    matchers: Vec<Arc<dyn Matcher>>,
+    name: String,
+}
+
+impl AndMatcher {
+    pub fn new(matchers: Vec<Arc<dyn Matcher>>) -> Self {
+        Self {
+            name: format!("and({})", joined_names(&matchers)),
+            matchers,
+        }
+    }
+}
+
+impl Matcher for AndMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        self.matchers.iter().all(|m| m.matches(ctx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        combined_dependency(&self.matchers)
+    }
+}
+
+/// 任意子 matcher 匹配时匹配（短路求值）。
+pub struct OrMatcher {
+    matchers: Vec<Arc<dyn Matcher>>,
+    name: String,
+}
+
+impl OrMatcher {
+    pub fn new(matchers: Vec<Arc<dyn Matcher>>) -> Self {
+        Self {
+            name: format!("or({})", joined_names(&matchers)),
+            matchers,
+        }
+    }
+}
+
+impl Matcher for OrMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        self.matchers.iter().any(|m| m.matches(ctx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        combined_dependency(&self.matchers)
+    }
+}
+
+/// 对子 matcher 取反。
+pub struct NotMatcher {
+    inner: Arc<dyn Matcher>,
+    name: String,
+}
+
+impl NotMatcher {
+    pub fn new(inner: Arc<dyn Matcher>) -> Self {
+        Self {
+            name: format!("not({})", inner.name()),
+            inner,
+        }
+    }
+}
+
+impl Matcher for NotMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        !self.inner.matches(ctx)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependency(&self) -> MatcherDependency {
+        self.inner.dependency()
+    }
+}
+
+/// Matcher 组合的便捷方法。
+///
+/// ```
+/// use umrouter::{Matcher, MatcherExt, RuntimeKind, RuntimeMatcher, TagMatcher};
+///
+/// let matcher = TagMatcher::new("auth-required")
+///     .and(RuntimeMatcher::new(RuntimeKind::Flutter).not());
+/// assert_eq!(
+///     matcher.name(),
+///     r#"and(tag("auth-required"), not(runtime(Flutter)))"#
+/// );
+/// ```
+pub trait MatcherExt: Matcher + Sized + 'static {
+    /// 与另一个 matcher 组合为 AND。
+    fn and(self, other: impl Matcher + 'static) -> AndMatcher {
+        AndMatcher::new(vec![Arc::new(self), Arc::new(other)])
+    }
+
+    /// 与另一个 matcher 组合为 OR。
+    fn or(self, other: impl Matcher + 'static) -> OrMatcher {
+        OrMatcher::new(vec![Arc::new(self), Arc::new(other)])
+    }
+
+    /// 取反。
+    fn not(self) -> NotMatcher {
+        NotMatcher::new(Arc::new(self))
+    }
+}
+
+impl<M: Matcher + 'static> MatcherExt for M {}
+
+//
+// ========== 辅助函数 ==========
+//
+
+/// 简单 glob 匹配：`*` 匹配任意长度字符，`?` 匹配单个字符。
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // 最近一次 `*` 的位置，以及它当时对应的 text 位置（用于回溯）。
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 按路径段判断 path 是否以 prefix 开头。
+pub(crate) fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.is_empty() || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// 所有子 matcher 都只依赖路由时，组合结果才只依赖路由。
+fn combined_dependency(matchers: &[Arc<dyn Matcher>]) -> MatcherDependency {
+    if matchers
+        .iter()
+        .all(|m| m.dependency() == MatcherDependency::RouteOnly)
+    {
+        MatcherDependency::RouteOnly
+    } else {
+        MatcherDependency::Request
+    }
+}
+
+fn quoted_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("{item:?}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn joined_names(matchers: &[Arc<dyn Matcher>]) -> String {
+    matchers
+        .iter()
+        .map(|m| m.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::test_support::route;
+    use crate::umrouter_core::types::CanonicalParams;
+
+    #[test]
+    fn glob_matches_wildcards() {
+        assert!(glob_match("orders.*", "orders.detail"));
+        assert!(glob_match("orders.*", "orders.detail.items"));
+        assert!(!glob_match("orders.*", "orders"));
+        assert!(glob_match("*.index", "home.index"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+    }
+
+    #[test]
+    fn path_prefix_respects_segments() {
+        assert!(path_has_prefix("/account", "/account"));
+        assert!(path_has_prefix("/account/settings", "/account/"));
+        assert!(!path_has_prefix("/accounts", "/account"));
+        assert!(path_has_prefix("/anything", "/"));
+    }
+
+    #[test]
+    fn matchers_evaluate_context() {
+        let meta = route("orders.detail", "/orders/detail", &["auth-required", "vip"]);
+        let stack = StackId("main".into());
+        let mut params = CanonicalParams::default();
+        params.map.insert("id".into(), "42".into());
+        let ctx = MatchContext {
+            route: &meta,
+            target_stack: &stack,
+            runtime: RuntimeKind::Flutter,
+            params: &params,
+        };
+
+        let cases: Vec<(Box<dyn Matcher>, bool)> = vec![
+            (Box::new(TagMatcher::new("vip")), true),
+            (Box::new(TagMatcher::new("admin-only")), false),
+            (
+                Box::new(AllTagsMatcher::new(["vip", "auth-required"])),
+                true,
+            ),
+            (Box::new(AllTagsMatcher::new(["vip", "admin-only"])), false),
+            (Box::new(AnyTagsMatcher::new(["admin-only", "vip"])), true),
+            (Box::new(AnyTagsMatcher::new(["admin-only", "beta"])), false),
+            (Box::new(PathPrefixMatcher::new("/orders")), true),
+            (Box::new(PathPrefixMatcher::new("/order")), false),
+            (Box::new(RuntimeMatcher::new(RuntimeKind::Flutter)), true),
+            (Box::new(RuntimeMatcher::new(RuntimeKind::Native)), false),
+            (Box::new(StackMatcher::new(StackId("main".into()))), true),
+            (Box::new(StackMatcher::new(StackId("auth".into()))), false),
+            (Box::new(ParamMatcher::present("id")), true),
+            (Box::new(ParamMatcher::present("debug")), false),
+            (Box::new(ParamMatcher::equals("id", "42")), true),
+            (Box::new(ParamMatcher::equals("id", "7")), false),
+            (
+                Box::new(TagMatcher::new("vip").and(ParamMatcher::present("id"))),
+                true,
+            ),
+            (
+                Box::new(TagMatcher::new("vip").and(ParamMatcher::present("debug"))),
+                false,
+            ),
+            (
+                Box::new(TagMatcher::new("beta").or(ParamMatcher::present("id"))),
+                true,
+            ),
+            (
+                Box::new(TagMatcher::new("beta").or(ParamMatcher::present("debug"))),
+                false,
+            ),
+            (Box::new(TagMatcher::new("beta").not()), true),
+            (Box::new(TagMatcher::new("vip").not()), false),
+        ];
+        for (matcher, expected) in cases {
+            assert_eq!(matcher.matches(&ctx), expected, "{}", matcher.name());
+        }
+    }
+
+    #[test]
+    fn combinator_names_are_descriptive() {
+        let matcher = TagMatcher::new("auth-required")
+            .and(RuntimeMatcher::new(RuntimeKind::Flutter).not())
+            .or(NameGlobMatcher::new("orders.*"));
+        assert_eq!(
+            matcher.name(),
+            r#"or(and(tag("auth-required"), not(runtime(Flutter))), name("orders.*"))"#
+        );
+    }
+}
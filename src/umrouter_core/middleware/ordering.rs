@@ -0,0 +1,132 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::types::Middleware;
+use crate::umrouter_core::types::MiddlewareId;
+
+/// 排序失败的原因。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum OrderingError {
+    /// 顺序约束形成环（按执行方向排列，首尾相接）。
+    Cycle(Vec<MiddlewareId>),
+
+    /// 顺序约束要求 `first` 先于 `then`，但两者所在分组的顺序相反。
+    GroupConflict {
+        first: MiddlewareId,
+        then: MiddlewareId,
+    },
+}
+
+/// 对同一阶段的中间件做稳定拓扑排序。
+///
+/// - `group` 把中间件分成若干组（例如全局 / 作用域），组号小的整体先执行
+/// - 约束来自 `runs_after` / `runs_before`，指向其他阶段的约束被忽略；
+///   与分组顺序相反的约束视为错误
+/// - 注册表保证约束只引用已注册的中间件（注册时检查，被引用的中间件不能注销）
+/// - 没有约束关系的中间件之间仍按 priority、id 排序
+pub(crate) fn topological_order(
+    middlewares: &[&Middleware],
+    group: impl Fn(&Middleware) -> u8,
+) -> Result<Vec<MiddlewareId>, OrderingError> {
+    let index: HashMap<&MiddlewareId, usize> = middlewares
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (&m.id, i))
+        .collect();
+
+    // edges[a] 包含 b 表示 a 必须先于 b 执行。
+    let mut edges = vec![Vec::new(); middlewares.len()];
+    let mut in_degree = vec![0usize; middlewares.len()];
+    for (i, m) in middlewares.iter().enumerate() {
+        let before = m
+            .runs_after
+            .iter()
+            .filter_map(|id| index.get(id))
+            .map(|&a| (a, i));
+        let after = m
+            .runs_before
+            .iter()
+            .filter_map(|id| index.get(id))
+            .map(|&b| (i, b));
+        for (from, to) in before.chain(after) {
+            if group(middlewares[from]) > group(middlewares[to]) {
+                return Err(OrderingError::GroupConflict {
+                    first: middlewares[from].id.clone(),
+                    then: middlewares[to].id.clone(),
+                });
+            }
+            if !edges[from].contains(&to) {
+                edges[from].push(to);
+                in_degree[to] += 1;
+            }
+        }
+    }
+
+    let key = |i: usize| {
+        let m = middlewares[i];
+        Reverse((group(m), m.priority, &m.id.0, i))
+    };
+    let mut ready: BinaryHeap<_> = (0..middlewares.len())
+        .filter(|&i| in_degree[i] == 0)
+        .map(key)
+        .collect();
+    let mut ordered = Vec::with_capacity(middlewares.len());
+    while let Some(Reverse((_, _, _, i))) = ready.pop() {
+        ordered.push(middlewares[i].id.clone());
+        for &next in &edges[i] {
+            in_degree[next] -= 1;
+            if in_degree[next] == 0 {
+                ready.push(key(next));
+            }
+        }
+    }
+
+    if ordered.len() == middlewares.len() {
+        Ok(ordered)
+    } else {
+        Err(OrderingError::Cycle(find_cycle(
+            middlewares,
+            &edges,
+            &in_degree,
+        )))
+    }
+}
+
+/// 在未能排序的节点中找出一个环。
+///
+/// 剩余节点的入度都大于 0，沿前驱一路回溯必然回到已访问的节点。
+fn find_cycle(
+    middlewares: &[&Middleware],
+    edges: &[Vec<usize>],
+    in_degree: &[usize],
+) -> Vec<MiddlewareId> {
+    let mut predecessor = vec![None; middlewares.len()];
+    for (from, targets) in edges.iter().enumerate() {
+        if in_degree[from] == 0 {
+            continue;
+        }
+        for &to in targets {
+            predecessor[to].get_or_insert(from);
+        }
+    }
+
+    let start = (0..middlewares.len())
+        .filter(|&i| in_degree[i] > 0)
+        .min_by(|&a, &b| middlewares[a].id.0.cmp(&middlewares[b].id.0))
+        .expect("unsorted nodes exist");
+    let mut path = vec![start];
+    let mut current = start;
+    loop {
+        current = predecessor[current].expect("remaining node has a remaining predecessor");
+        if let Some(pos) = path.iter().position(|&i| i == current) {
+            // 回溯方向与执行方向相反；翻转后把起点（id 最小）放回首位。
+            let mut cycle: Vec<_> = path[pos..].iter().rev().collect();
+            cycle.rotate_right(1);
+            return cycle
+                .into_iter()
+                .map(|&i| middlewares[i].id.clone())
+                .collect();
+        }
+        path.push(current);
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+use super::async_executor::MiddlewareExecutor;
+use super::failure::FailurePolicy;
+use super::types::{
+    AlwaysMatcher, ExecuteContext, Executor, Middleware, MiddlewarePhase, MiddlewareResult,
+    NavRewrite,
+};
+use crate::umrouter_core::route::RouteMeta;
+use crate::umrouter_core::types::{CanonicalParams, LifecycleEvent, MiddlewareId};
+
+/// 参数校验核心中间件的 id。
+pub const CORE_PARAM_VALIDATION_ID: &str = "core.param_validation";
+
+/// hook 核心中间件的 id。
+pub const CORE_HOOK_ID: &str = "core.hook";
+
+/// 启用子 schema 时，各子 schema 在 SchemaRegistry 中的后缀。
+pub const SUB_SCHEMA_SUFFIXES: [&str; 3] = ["path", "query", "body"];
+
+//
+// ========== 参数校验 ==========
+//
+
+/// JSON Schema 编译失败。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// schema id。
+    pub schema_id: String,
+
+    /// 编译错误信息。
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid schema `{}`: {}", self.schema_id, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// 已编译的参数 schema 表：schema_id -> Validator。
+///
+/// 路由的 `ParamSchemaSpec.has_sub_schemas` 为 true 时，
+/// 按 "{schema_id}.path" / ".query" / ".body" 查找子 schema，三份都必须注册；
+/// 每份子 schema 都对合并后的参数整体校验（通常只约束自己关心的字段）。
+#[derive(Default)]
+pub struct SchemaRegistry {
+    validators: HashMap<String, Validator>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 编译并注册一份 schema，同 id 的旧 schema 会被覆盖。
+    pub fn register(
+        &mut self,
+        schema_id: impl Into<String>,
+        schema: &Value,
+    ) -> Result<(), SchemaError> {
+        let schema_id = schema_id.into();
+        let validator = jsonschema::validator_for(schema).map_err(|e| SchemaError {
+            schema_id: schema_id.clone(),
+            message: e.to_string(),
+        })?;
+        self.validators.insert(schema_id, validator);
+        Ok(())
+    }
+
+    /// 是否已注册该 schema。
+    pub fn contains(&self, schema_id: &str) -> bool {
+        self.validators.contains_key(schema_id)
+    }
+
+    /// 用指定 schema 校验参数，返回所有校验错误。
+    ///
+    /// schema 未注册时返回 None。
+    pub fn validate(&self, schema_id: &str, params: &CanonicalParams) -> Option<Vec<String>> {
+        let validator = self.validators.get(schema_id)?;
+        let instance = Value::Object(
+            params
+                .map
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        );
+        Some(
+            validator
+                .iter_errors(&instance)
+                .map(|e| format!("{}: {}", e.instance_path(), e))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Debug for SchemaRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaRegistry")
+            .field("schemas", &self.validators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// 核心参数校验中间件。
+///
+/// 按路由的 `ParamSchemaSpec` 校验参数；校验失败或引用了未注册的 schema
+/// （包括缺少任一子 schema）时以 "InvalidParams" 中止导航。未声明 schema 的路由直接通过。
+///
+/// schema 表与注册表共享，之后通过 `MiddlewareRegistry::register_schema` 注册的 schema 立即生效。
+#[derive(Debug, Clone, Default)]
+pub struct CoreParamValidationMiddleware {
+    schemas: Arc<RwLock<SchemaRegistry>>,
+}
+
+impl CoreParamValidationMiddleware {
+    pub fn new(schemas: Arc<RwLock<SchemaRegistry>>) -> Self {
+        Self { schemas }
+    }
+
+    /// 使用的 schema 表。
+    pub fn schemas(&self) -> RwLockReadGuard<'_, SchemaRegistry> {
+        self.schemas.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Executor for CoreParamValidationMiddleware {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        match validate_route_params(&self.schemas(), ctx.route, ctx.params) {
+            Ok(()) => MiddlewareResult::Continue,
+            Err(detail) => MiddlewareResult::Abort {
+                reason: format!("InvalidParams: {detail}"),
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        CORE_PARAM_VALIDATION_ID
+    }
+}
+
+/// 按路由的 `ParamSchemaSpec` 校验参数，失败时返回错误描述。
+pub(crate) fn validate_route_params(
+    schemas: &SchemaRegistry,
+    route: &RouteMeta,
+    params: &CanonicalParams,
+) -> Result<(), String> {
+    let spec = &route.param_schema;
+    let Some(schema_id) = &spec.schema_id else {
+        return Ok(());
+    };
+
+    let schema_ids: Vec<String> = if spec.has_sub_schemas {
+        SUB_SCHEMA_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{schema_id}.{suffix}"))
+            .collect()
+    } else {
+        vec![schema_id.clone()]
+    };
+
+    let mut errors = Vec::new();
+    for id in &schema_ids {
+        match schemas.validate(id, params) {
+            Some(found) => errors.extend(found),
+            None => return Err(format!("unknown schema `{id}`")),
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+//
+// ========== Hook ==========
+//
+
+/// 业务自定义 hook 表：hook key -> 执行器。
+///
+/// hook 与中间件共用 Executor 接口，可以修改参数、中止或重定向导航。
+#[derive(Default, Clone)]
+pub struct HookRegistry {
+    hooks: HashMap<String, Arc<dyn Executor>>,
+}
+
+impl HookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册一个 hook，同 key 的旧 hook 会被覆盖。
+    pub fn register(&mut self, key: impl Into<String>, hook: impl Executor + 'static) {
+        self.hooks.insert(key.into(), Arc::new(hook));
+    }
+
+    /// 根据 key 获取 hook。
+    pub fn get(&self, key: &str) -> Option<&Arc<dyn Executor>> {
+        self.hooks.get(key)
+    }
+}
+
+impl fmt::Debug for HookRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookRegistry")
+            .field("hooks", &self.hooks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// 目标路由声明要监听的生命周期事件。
+///
+/// 由 CoreHookMiddleware 写入 Extensions，供后续的命令生成转发给 runtime。
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LifecycleSubscriptions(pub Vec<LifecycleEvent>);
+
+/// 核心 hook 中间件。
+///
+/// 按路由 `HookSpec.custom_hooks` 的顺序依次执行自定义 hook：
+/// - 任一 hook 返回 Abort / Redirect 时立即返回该结果
+/// - 各 hook 返回的 Rewrite 按顺序合并，全部执行完后一并返回
+/// - 路由引用了未注册的 hook 时以 "HookDenied" 中止导航（hook 不允许被绕过）
+///
+/// 同时把 `HookSpec.enabled_lifecycles` 写入 Extensions（`LifecycleSubscriptions`）。
+/// hook 表与注册表共享，之后通过 `MiddlewareRegistry::register_hook` 注册的 hook 立即生效。
+#[derive(Debug, Clone, Default)]
+pub struct CoreHookMiddleware {
+    hooks: Arc<RwLock<HookRegistry>>,
+}
+
+impl CoreHookMiddleware {
+    pub fn new(hooks: Arc<RwLock<HookRegistry>>) -> Self {
+        Self { hooks }
+    }
+
+    /// 使用的 hook 表。
+    pub fn hooks(&self) -> RwLockReadGuard<'_, HookRegistry> {
+        self.hooks.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Executor for CoreHookMiddleware {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        let spec = &ctx.route.hook_spec;
+        ctx.extensions
+            .insert(LifecycleSubscriptions(spec.enabled_lifecycles.clone()));
+
+        let mut rewrite = NavRewrite::new();
+        for key in &spec.custom_hooks {
+            // 执行前释放读锁，hook 内部可以安全地读取 hook 表。
+            let Some(hook) = self.hooks().get(key).cloned() else {
+                return MiddlewareResult::Abort {
+                    reason: format!("HookDenied: hook `{key}` is not registered"),
+                };
+            };
+            match hook.execute(ctx) {
+                MiddlewareResult::Continue => {}
+                MiddlewareResult::Rewrite(later) => rewrite = rewrite.merge(later),
+                result => return result,
+            }
+        }
+        if rewrite.is_empty() {
+            MiddlewareResult::Continue
+        } else {
+            MiddlewareResult::Rewrite(rewrite)
+        }
+    }
+
+    fn name(&self) -> &str {
+        CORE_HOOK_ID
+    }
+}
+
+//
+// ========== 安装 ==========
+//
+
+/// 构造固定顺序的核心中间件：先参数校验，再执行 hook。
+///
+/// 核心中间件始终匹配、失败时 fail-closed。
+pub(crate) fn core_middlewares(
+    schemas: Arc<RwLock<SchemaRegistry>>,
+    hooks: Arc<RwLock<HookRegistry>>,
+) -> [Middleware; 2] {
+    let core = |id: &str, executor: MiddlewareExecutor, priority| {
+        Middleware::new(
+            MiddlewareId(id.into()),
+            Arc::new(AlwaysMatcher),
+            executor,
+            MiddlewarePhase::Core,
+        )
+        .with_priority(priority)
+        .with_failure_policy(FailurePolicy::FailClosed)
+    };
+    [
+        core(
+            CORE_PARAM_VALIDATION_ID,
+            MiddlewareExecutor::sync(CoreParamValidationMiddleware::new(schemas)),
+            0,
+        ),
+        core(
+            CORE_HOOK_ID,
+            MiddlewareExecutor::sync(CoreHookMiddleware::new(hooks)),
+            1,
+        )
+        .with_runs_after([CORE_PARAM_VALIDATION_ID]),
+    ]
+}
+
+/// 是否为核心中间件的 id。
+pub fn is_core_middleware(id: &MiddlewareId) -> bool {
+    id.0 == CORE_PARAM_VALIDATION_ID || id.0 == CORE_HOOK_ID
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::umrouter_core::middleware::{FnExecutor, MiddlewareRegistry, RegistryError};
+    use crate::umrouter_core::pipeline::{NavigationContext, PipelineResult, PipelineRunner};
+    use crate::umrouter_core::test_support::{block_on, middleware, noop, route};
+
+    #[test]
+    fn core_chain_validates_then_runs_hooks() {
+        let mut schemas = SchemaRegistry::new();
+        schemas
+            .register("order", &json!({"type": "object", "required": ["orderId"]}))
+            .unwrap();
+        let mut hooks = HookRegistry::new();
+        hooks.register(
+            "audit",
+            FnExecutor::new("audit", |ctx: &mut ExecuteContext| {
+                ctx.extensions.insert_str("audited", "yes");
+                MiddlewareResult::Continue
+            }),
+        );
+        let mut registry = MiddlewareRegistry::with_core(schemas, hooks);
+
+        let mut meta = route("orders.detail", "/orders/:orderId", &[]);
+        meta.param_schema.schema_id = Some("order".into());
+        meta.hook_spec.custom_hooks = vec!["audit".into()];
+        meta.hook_spec.enabled_lifecycles = vec![LifecycleEvent::OnAppear];
+
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).core_chain;
+        assert_eq!(
+            chain,
+            [CORE_PARAM_VALIDATION_ID, CORE_HOOK_ID].map(|id| MiddlewareId(id.into()))
+        );
+
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(
+            matches!(outcome.result, PipelineResult::Aborted { ref reason, .. } if reason.starts_with("InvalidParams"))
+        );
+        assert_eq!(nav.extensions.get_str("audited"), None);
+
+        nav.params.map.insert("orderId".into(), json!("42"));
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(nav.extensions.get_str("audited"), Some("yes"));
+        assert_eq!(
+            nav.extensions.get::<LifecycleSubscriptions>().unwrap().0,
+            [LifecycleEvent::OnAppear]
+        );
+
+        // 未注册的 hook 不能被绕过。
+        let params = nav.params.clone();
+        meta.hook_spec.custom_hooks = vec!["missing".into()];
+        let mut nav = NavigationContext::new(&meta, params);
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(!outcome.is_completed());
+
+        // 启用子 schema 时缺少任何一份都会中止；之后注册的 schema 与 hook 立即生效。
+        let mut meta = route("orders.create", "/orders/new", &[]);
+        meta.param_schema.schema_id = Some("create".into());
+        meta.param_schema.has_sub_schemas = true;
+        meta.hook_spec.custom_hooks = vec!["late".into()];
+        for suffix in ["path", "query"] {
+            registry
+                .register_schema(format!("create.{suffix}"), &json!({"type": "object"}))
+                .unwrap();
+        }
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(
+            matches!(outcome.result, PipelineResult::Aborted { ref reason, .. } if reason.contains("create.body"))
+        );
+        registry
+            .register_schema("create.body", &json!({"type": "object"}))
+            .unwrap();
+        registry.register_hook("late", noop());
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+
+        // Core 阶段与核心中间件受保护。
+        let id = MiddlewareId(CORE_HOOK_ID.into());
+        assert_eq!(
+            registry.register(middleware(
+                "biz",
+                MiddlewarePhase::Core,
+                0,
+                AlwaysMatcher,
+                noop()
+            )),
+            Err(RegistryError::CorePhaseReserved(MiddlewareId("biz".into())))
+        );
+        assert_eq!(
+            registry.set_enabled(&id, false),
+            Err(RegistryError::ProtectedMiddleware(id.clone()))
+        );
+        assert!(registry.unregister(&id).is_err());
+    }
+}
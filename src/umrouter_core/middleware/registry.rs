@@ -1,35 +1,339 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-use super::types::{Middleware, MiddlewarePhase};
-use crate::umrouter_core::types::MiddlewareId;
+use super::failure::{FailurePolicy, MiddlewareFailure, catch_matcher};
+use super::ordering::{OrderingError, topological_order};
+use super::protected::{
+    HookRegistry, SchemaError, SchemaRegistry, core_middlewares, is_core_middleware,
+    validate_route_params,
+};
+use super::types::{Executor, MatchContext, Middleware, MiddlewarePhase};
+use crate::umrouter_core::pipeline::ResolvedMiddlewareChain;
+use crate::umrouter_core::route::RouteMeta;
+use crate::umrouter_core::types::{CanonicalParams, MiddlewareId};
+
+/// 默认的 panic 熔断阈值。
+pub const DEFAULT_PANIC_THRESHOLD: u32 = 3;
 
 /// 中间件注册表。
 ///
 /// 集中管理所有注册的中间件。
-#[derive(Default)]
+/// 创建时自动安装核心中间件（参数校验 -> hook），它们占据整个 Core 阶段，
+/// 不能被注销、替换或停用，也不会因 panic 被熔断。
 pub struct MiddlewareRegistry {
     /// 所有注册的中间件：id -> Middleware
     middlewares: HashMap<MiddlewareId, Middleware>,
+
+    /// 各阶段的执行顺序（含已停用的中间件），注册表变更时重新计算。
+    order: HashMap<MiddlewarePhase, Vec<MiddlewareId>>,
+
+    /// 被手动停用的中间件：保留注册信息，但解析中间件链时跳过。
+    disabled: HashSet<MiddlewareId>,
+
+    /// 注册表版本号，每次变更递增，供中间件链缓存判断是否失效。
+    ///
+    /// 熔断发生在执行期间（只持有 &self），因此使用原子类型。
+    version: AtomicU64,
+
+    /// 各中间件累计 panic 次数。
+    panic_counts: Mutex<HashMap<MiddlewareId, u32>>,
+
+    /// 累计 panic 达到该次数后，fail-open 中间件被自动停用；None 表示不自动停用。
+    panic_threshold: Option<u32>,
+
+    /// 参数校验核心中间件使用的 schema 表。
+    schemas: Arc<RwLock<SchemaRegistry>>,
+
+    /// hook 核心中间件使用的 hook 表。
+    hooks: Arc<RwLock<HookRegistry>>,
+}
+
+impl Default for MiddlewareRegistry {
+    fn default() -> Self {
+        Self::with_core(SchemaRegistry::default(), HookRegistry::default())
+    }
+}
+
+/// 中间件注册表操作错误。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// 中间件 id 已注册。
+    DuplicateId(MiddlewareId),
+
+    /// 中间件 id 未注册。
+    UnknownId(MiddlewareId),
+
+    /// 业务中间件不能注册到 Core 阶段。
+    CorePhaseReserved(MiddlewareId),
+
+    /// 核心中间件不能被注销、替换或停用。
+    ProtectedMiddleware(MiddlewareId),
+
+    /// `runs_after` / `runs_before` 引用了未注册的中间件。
+    UnknownDependency {
+        id: MiddlewareId,
+        dependency: MiddlewareId,
+    },
+
+    /// 仍被其他中间件的 `runs_after` / `runs_before` 引用，不能注销。
+    ///
+    /// `by` 为引用它的中间件（按 id 排序）。
+    Referenced {
+        id: MiddlewareId,
+        by: Vec<MiddlewareId>,
+    },
+
+    /// 顺序约束形成环（按执行方向排列，最后一个指回第一个）。
+    Cycle(Vec<MiddlewareId>),
+
+    /// 顺序约束要求 `first` 先于 `then`，与作用域规定的顺序相反
+    /// （PreRW 中全局先于作用域中间件，PostRO 中作用域先于全局中间件）。
+    ScopeConflict {
+        first: MiddlewareId,
+        then: MiddlewareId,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateId(id) => write!(f, "middleware `{}` is already registered", id.0),
+            Self::UnknownId(id) => write!(f, "middleware `{}` is not registered", id.0),
+            Self::CorePhaseReserved(id) => write!(
+                f,
+                "middleware `{}` cannot be registered into the reserved Core phase",
+                id.0
+            ),
+            Self::ProtectedMiddleware(id) => {
+                write!(f, "core middleware `{}` cannot be modified", id.0)
+            }
+            Self::UnknownDependency { id, dependency } => write!(
+                f,
+                "middleware `{}` has an ordering constraint on unknown middleware `{}`",
+                id.0, dependency.0
+            ),
+            Self::Referenced { id, by } => {
+                let by: Vec<&str> = by.iter().map(|id| id.0.as_str()).collect();
+                write!(
+                    f,
+                    "middleware `{}` is still referenced by ordering constraints of {}",
+                    id.0,
+                    by.join(", ")
+                )
+            }
+            Self::Cycle(ids) => {
+                let ids: Vec<&str> = ids.iter().map(|id| id.0.as_str()).collect();
+                write!(
+                    f,
+                    "middleware ordering cycle: {} -> {}",
+                    ids.join(" -> "),
+                    ids[0]
+                )
+            }
+            Self::ScopeConflict { first, then } => write!(
+                f,
+                "ordering constraint `{}` before `{}` contradicts scope ordering",
+                first.0, then.0
+            ),
+        }
+    }
 }
 
-impl std::fmt::Debug for MiddlewareRegistry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl std::error::Error for RegistryError {}
+
+/// 中间件在所属阶段中的分组：组号小的先执行。
+///
+/// PreRW：全局 -> 作用域；PostRO：作用域 -> 全局；Core 不分组。
+fn scope_group(phase: MiddlewarePhase, middleware: &Middleware) -> u8 {
+    match (phase, middleware.scope.is_global()) {
+        (MiddlewarePhase::PreRW, false) | (MiddlewarePhase::PostRO, true) => 1,
+        _ => 0,
+    }
+}
+
+impl fmt::Debug for MiddlewareRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MiddlewareRegistry")
             .field("count", &self.middlewares.len())
             .field("ids", &self.middlewares.keys().collect::<Vec<_>>())
+            .field("disabled", &self.disabled)
+            .field("version", &self.version())
+            .field("panic_threshold", &self.panic_threshold)
             .finish()
     }
 }
 
 impl MiddlewareRegistry {
-    /// 创建空的注册表。
+    /// 创建注册表，核心中间件使用空的 schema 表与 hook 表。
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// 创建注册表，核心中间件使用给定的 schema 表与 hook 表。
+    pub fn with_core(schemas: SchemaRegistry, hooks: HookRegistry) -> Self {
+        let mut registry = Self {
+            middlewares: HashMap::new(),
+            order: HashMap::new(),
+            disabled: HashSet::new(),
+            version: AtomicU64::new(0),
+            panic_counts: Mutex::new(HashMap::new()),
+            panic_threshold: Some(DEFAULT_PANIC_THRESHOLD),
+            schemas: Arc::new(RwLock::new(schemas)),
+            hooks: Arc::new(RwLock::new(hooks)),
+        };
+        let core = core_middlewares(Arc::clone(&registry.schemas), Arc::clone(&registry.hooks));
+        for middleware in core {
+            registry
+                .middlewares
+                .insert(middleware.id.clone(), middleware);
+        }
+        registry
+            .rebuild_order()
+            .expect("core middlewares have a fixed order");
+        registry
+    }
+
+    /// 编译并注册一份参数 schema，同 id 的旧 schema 会被覆盖，之后的导航立即使用。
+    pub fn register_schema(
+        &mut self,
+        schema_id: impl Into<String>,
+        schema: &serde_json::Value,
+    ) -> Result<(), SchemaError> {
+        self.schemas
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .register(schema_id, schema)
+    }
+
+    /// 用参数校验核心中间件的规则校验参数，失败时返回错误描述。
+    ///
+    /// 供不经过完整 pipeline 的调用方（例如导航事务）复用同一份 schema 表。
+    pub fn validate_params(
+        &self,
+        route: &RouteMeta,
+        params: &CanonicalParams,
+    ) -> Result<(), String> {
+        let schemas = self.schemas.read().unwrap_or_else(|e| e.into_inner());
+        validate_route_params(&schemas, route, params)
+    }
+
+    /// 注册一个自定义 hook，同 key 的旧 hook 会被覆盖，之后的导航立即使用。
+    pub fn register_hook(&mut self, key: impl Into<String>, hook: impl Executor + 'static) {
+        self.hooks
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .register(key, hook);
+    }
+
     /// 注册一个中间件。
-    pub fn register(&mut self, middleware: Middleware) {
-        self.middlewares.insert(middleware.id.clone(), middleware);
+    ///
+    /// id 已存在时返回 `DuplicateId`；需要覆盖请使用 `replace`。
+    /// Core 阶段保留给核心中间件，注册到该阶段返回 `CorePhaseReserved`。
+    /// 顺序约束引用了未注册的中间件或形成环时返回错误，注册表保持不变。
+    pub fn register(&mut self, middleware: Middleware) -> Result<(), RegistryError> {
+        if self.middlewares.contains_key(&middleware.id) {
+            return Err(RegistryError::DuplicateId(middleware.id));
+        }
+        if middleware.phase == MiddlewarePhase::Core {
+            return Err(RegistryError::CorePhaseReserved(middleware.id));
+        }
+        self.check_dependencies(&middleware)?;
+        let id = middleware.id.clone();
+        self.middlewares.insert(id.clone(), middleware);
+        if let Err(e) = self.rebuild_order() {
+            self.middlewares.remove(&id);
+            return Err(e);
+        }
+        self.bump_version();
+        Ok(())
+    }
+
+    /// 注销一个中间件，返回被移除的中间件。
+    ///
+    /// 仍被其他中间件的顺序约束引用时返回 `Referenced`，需要先注销或替换引用方。
+    /// 停用状态与 panic 计数一并清除。
+    pub fn unregister(&mut self, id: &MiddlewareId) -> Result<Middleware, RegistryError> {
+        self.check_unprotected(id)?;
+        let mut by: Vec<MiddlewareId> = self
+            .middlewares
+            .values()
+            .filter(|m| m.id != *id && (m.runs_after.contains(id) || m.runs_before.contains(id)))
+            .map(|m| m.id.clone())
+            .collect();
+        if !by.is_empty() {
+            by.sort_by(|a, b| a.0.cmp(&b.0));
+            return Err(RegistryError::Referenced { id: id.clone(), by });
+        }
+        let middleware = self
+            .middlewares
+            .remove(id)
+            .ok_or_else(|| RegistryError::UnknownId(id.clone()))?;
+        self.disabled.remove(id);
+        self.lock_panic_counts().remove(id);
+        // 移除节点不会产生环。
+        self.rebuild_order()
+            .expect("removing a middleware cannot introduce a cycle");
+        self.bump_version();
+        Ok(middleware)
+    }
+
+    /// 用同 id 的新中间件替换已注册的中间件，返回旧的中间件。
+    ///
+    /// 启用/停用状态保持不变，panic 计数清零。
+    pub fn replace(&mut self, middleware: Middleware) -> Result<Middleware, RegistryError> {
+        self.check_unprotected(&middleware.id)?;
+        if !self.middlewares.contains_key(&middleware.id) {
+            return Err(RegistryError::UnknownId(middleware.id));
+        }
+        if middleware.phase == MiddlewarePhase::Core {
+            return Err(RegistryError::CorePhaseReserved(middleware.id));
+        }
+        self.check_dependencies(&middleware)?;
+        let old = self
+            .middlewares
+            .insert(middleware.id.clone(), middleware)
+            .expect("checked above");
+        if let Err(e) = self.rebuild_order() {
+            self.middlewares.insert(old.id.clone(), old);
+            return Err(e);
+        }
+        self.lock_panic_counts().remove(&old.id);
+        self.bump_version();
+        Ok(old)
+    }
+
+    /// 启用或停用一个中间件。
+    ///
+    /// 停用的中间件保留在注册表中，但解析中间件链时会被跳过，
+    /// 可用于远程关闭出问题的中间件。重新启用时清零 panic 计数（解除熔断）。
+    pub fn set_enabled(&mut self, id: &MiddlewareId, enabled: bool) -> Result<(), RegistryError> {
+        self.check_unprotected(id)?;
+        if !self.middlewares.contains_key(id) {
+            return Err(RegistryError::UnknownId(id.clone()));
+        }
+        let changed = if enabled {
+            let was_tripped = self.is_tripped(id);
+            self.lock_panic_counts().remove(id);
+            self.disabled.remove(id) || was_tripped
+        } else {
+            self.disabled.insert(id.clone())
+        };
+        if changed {
+            self.bump_version();
+        }
+        Ok(())
+    }
+
+    /// 中间件是否已注册且未被手动停用。
+    pub fn is_enabled(&self, id: &MiddlewareId) -> bool {
+        self.middlewares.contains_key(id) && !self.disabled.contains(id)
+    }
+
+    /// 中间件是否参与执行：已启用且未被熔断。
+    pub fn is_active(&self, id: &MiddlewareId) -> bool {
+        self.is_enabled(id) && !self.is_tripped(id)
     }
 
     /// 根据 ID 获取中间件。
@@ -47,6 +351,166 @@ impl MiddlewareRegistry {
         self.middlewares.values().filter(move |m| m.phase == phase)
     }
 
+    /// 按阶段获取中间件，并按执行顺序排好。
+    ///
+    /// 先满足 `runs_after` / `runs_before` 约束（稳定拓扑序），
+    /// 其余按 priority 从小到大、priority 相同时按 id 排序。
+    /// 已停用或已熔断的中间件不会出现在结果中，但它们带来的顺序约束仍然生效。
+    pub fn ordered_by_phase(&self, phase: MiddlewarePhase) -> Vec<&Middleware> {
+        self.order
+            .get(&phase)
+            .into_iter()
+            .flatten()
+            .filter(|id| self.is_active(id))
+            .filter_map(|id| self.middlewares.get(id))
+            .collect()
+    }
+
+    /// 对当前请求执行所有 matcher，解析出中间件链（不使用缓存）。
+    ///
+    /// 作用域不包含目标路由的中间件不会执行 matcher。
+    pub fn resolve_chain(&self, ctx: &MatchContext) -> ResolvedMiddlewareChain {
+        let mut failures = Vec::new();
+        let mut matched = |phase| {
+            self.ordered_by_phase(phase)
+                .into_iter()
+                .filter(|m| m.scope.contains(ctx.route))
+                .filter(|m| {
+                    self.evaluate(m, ctx).unwrap_or_else(|f| {
+                        failures.push(f);
+                        false
+                    })
+                })
+                .map(|m| m.id.clone())
+                .collect()
+        };
+        let mut chain = ResolvedMiddlewareChain {
+            pre_rw_chain: matched(MiddlewarePhase::PreRW),
+            core_chain: matched(MiddlewarePhase::Core),
+            post_ro_chain: matched(MiddlewarePhase::PostRO),
+            failures,
+            ..Default::default()
+        };
+        chain.record_scopes(self);
+        chain
+    }
+
+    /// 执行中间件的 matcher；panic 会被捕获、计数并转换为 MiddlewareFailure。
+    pub fn evaluate(
+        &self,
+        middleware: &Middleware,
+        ctx: &MatchContext,
+    ) -> Result<bool, MiddlewareFailure> {
+        catch_matcher(middleware, ctx).inspect_err(|f| self.record_failure(f))
+    }
+
+    /// 记录一次 panic；累计次数达到阈值时熔断该中间件。
+    pub fn record_failure(&self, failure: &MiddlewareFailure) {
+        let mut counts = self.lock_panic_counts();
+        let count = counts.entry(failure.id.clone()).or_insert(0);
+        *count += 1;
+        if self.panic_threshold == Some(*count) {
+            self.bump_version();
+        }
+    }
+
+    /// 中间件累计 panic 次数。
+    pub fn panic_count(&self, id: &MiddlewareId) -> u32 {
+        self.lock_panic_counts().get(id).copied().unwrap_or(0)
+    }
+
+    /// 中间件是否因反复 panic 被熔断。
+    ///
+    /// 核心中间件与 fail-closed 中间件永不熔断：停用 fail-closed 中间件等于把它变成 fail-open。
+    pub fn is_tripped(&self, id: &MiddlewareId) -> bool {
+        !is_core_middleware(id)
+            && self
+                .middlewares
+                .get(id)
+                .is_none_or(|m| m.failure_policy != FailurePolicy::FailClosed)
+            && self
+                .panic_threshold
+                .is_some_and(|threshold| self.panic_count(id) >= threshold)
+    }
+
+    /// 清零中间件的 panic 计数（同时解除熔断）。
+    pub fn reset_failures(&self, id: &MiddlewareId) {
+        let was_tripped = self.is_tripped(id);
+        self.lock_panic_counts().remove(id);
+        if was_tripped {
+            self.bump_version();
+        }
+    }
+
+    /// 设置 panic 熔断阈值；None 表示永不自动停用。
+    pub fn set_panic_threshold(&mut self, threshold: Option<u32>) {
+        self.panic_threshold = threshold;
+        self.bump_version();
+    }
+
+    fn check_unprotected(&self, id: &MiddlewareId) -> Result<(), RegistryError> {
+        if is_core_middleware(id) {
+            Err(RegistryError::ProtectedMiddleware(id.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 检查顺序约束引用的中间件是否都已注册（或就是自身）。
+    fn check_dependencies(&self, middleware: &Middleware) -> Result<(), RegistryError> {
+        let mut dependencies: Vec<&MiddlewareId> = middleware
+            .runs_after
+            .iter()
+            .chain(&middleware.runs_before)
+            .collect();
+        dependencies.sort_by(|a, b| a.0.cmp(&b.0));
+        match dependencies
+            .into_iter()
+            .find(|dep| **dep != middleware.id && !self.middlewares.contains_key(dep))
+        {
+            Some(dependency) => Err(RegistryError::UnknownDependency {
+                id: middleware.id.clone(),
+                dependency: dependency.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// 重新计算各阶段的执行顺序；存在环时保留原顺序并返回错误。
+    fn rebuild_order(&mut self) -> Result<(), RegistryError> {
+        let mut order = HashMap::new();
+        for phase in [
+            MiddlewarePhase::PreRW,
+            MiddlewarePhase::Core,
+            MiddlewarePhase::PostRO,
+        ] {
+            let members: Vec<&Middleware> = self.by_phase(phase).collect();
+            let ordered =
+                topological_order(&members, |m| scope_group(phase, m)).map_err(|e| match e {
+                    OrderingError::Cycle(ids) => RegistryError::Cycle(ids),
+                    OrderingError::GroupConflict { first, then } => {
+                        RegistryError::ScopeConflict { first, then }
+                    }
+                })?;
+            order.insert(phase, ordered);
+        }
+        self.order = order;
+        Ok(())
+    }
+
+    /// 注册表当前版本号。
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    fn bump_version(&self) {
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn lock_panic_counts(&self) -> std::sync::MutexGuard<'_, HashMap<MiddlewareId, u32>> {
+        self.panic_counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// 获取中间件数量。
     pub fn len(&self) -> usize {
         self.middlewares.len()
@@ -57,3 +521,178 @@ impl MiddlewareRegistry {
         self.middlewares.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::middleware::{AlwaysMatcher, MiddlewareScope};
+    use crate::umrouter_core::pipeline::NavigationContext;
+    use crate::umrouter_core::test_support::{middleware, noop, route};
+    use crate::umrouter_core::types::CanonicalParams;
+
+    #[test]
+    fn enable_disable_unregister_and_replace() {
+        let a = || middleware("a", MiddlewarePhase::PreRW, 0, AlwaysMatcher, noop());
+        let b = middleware("b", MiddlewarePhase::PreRW, 1, AlwaysMatcher, noop());
+        let id = MiddlewareId("a".into());
+
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(a()).unwrap();
+        registry.register(b).unwrap();
+        assert_eq!(
+            registry.register(a()),
+            Err(RegistryError::DuplicateId(id.clone()))
+        );
+
+        let meta = route("home.index", "/home", &[]);
+        let nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = |registry: &MiddlewareRegistry| {
+            registry.resolve_chain(&nav.match_context()).pre_rw_chain
+        };
+
+        let version = registry.version();
+        registry.set_enabled(&id, false).unwrap();
+        assert!(registry.version() > version);
+        assert!(!registry.is_enabled(&id) && registry.get(&id).is_some());
+        assert_eq!(chain(&registry), vec![MiddlewareId("b".into())]);
+
+        registry.replace(a().with_priority(5)).unwrap();
+        assert!(!registry.is_enabled(&id));
+        registry.set_enabled(&id, true).unwrap();
+        assert_eq!(chain(&registry), vec![MiddlewareId("b".into()), id.clone()]);
+
+        registry.unregister(&id).unwrap();
+        assert_eq!(
+            registry.unregister(&id).unwrap_err(),
+            RegistryError::UnknownId(id.clone())
+        );
+        assert!(registry.replace(a()).is_err());
+        // 剩下 b 与两个核心中间件。
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn orders_by_constraints_then_priority() {
+        let mw =
+            |id, priority| middleware(id, MiddlewarePhase::PreRW, priority, AlwaysMatcher, noop());
+        let ids = |names: &[&str]| {
+            names
+                .iter()
+                .map(|n| MiddlewareId((*n).into()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(mw("auth", 100)).unwrap();
+        registry.register(mw("trace", 50)).unwrap();
+        registry
+            .register(mw("experiment", 0).with_runs_after(["auth"]))
+            .unwrap();
+        registry
+            .register(mw("session", 200).with_runs_before(["auth"]))
+            .unwrap();
+        let ordered: Vec<_> = registry
+            .ordered_by_phase(MiddlewarePhase::PreRW)
+            .into_iter()
+            .map(|m| m.id.clone())
+            .collect();
+        assert_eq!(ordered, ids(&["trace", "session", "auth", "experiment"]));
+
+        assert_eq!(
+            registry.register(mw("late", 0).with_runs_after(["missing"])),
+            Err(RegistryError::UnknownDependency {
+                id: MiddlewareId("late".into()),
+                dependency: MiddlewareId("missing".into()),
+            })
+        );
+        assert_eq!(
+            registry
+                .replace(mw("auth", 100).with_runs_after(["experiment"]))
+                .unwrap_err(),
+            RegistryError::Cycle(ids(&["auth", "experiment"]))
+        );
+        // 失败的操作不会改变注册表。
+        assert!(
+            registry
+                .get(&MiddlewareId("auth".into()))
+                .unwrap()
+                .runs_after
+                .is_empty()
+        );
+        assert!(registry.get(&MiddlewareId("late".into())).is_none());
+
+        // 被顺序约束引用的中间件不能注销，先注销引用方即可。
+        let auth = MiddlewareId("auth".into());
+        assert_eq!(
+            registry.unregister(&auth).unwrap_err(),
+            RegistryError::Referenced {
+                id: auth.clone(),
+                by: ids(&["experiment", "session"]),
+            }
+        );
+        for id in ids(&["experiment", "session", "auth"]) {
+            registry.unregister(&id).unwrap();
+        }
+    }
+
+    #[test]
+    fn scoped_middleware_wraps_inside_global() {
+        let mw = |id, phase, priority| middleware(id, phase, priority, AlwaysMatcher, noop());
+        let ids = |names: &[&str]| {
+            names
+                .iter()
+                .map(|n| MiddlewareId((*n).into()))
+                .collect::<Vec<_>>()
+        };
+        let orders = MiddlewareScope::namespace("orders.*");
+        let account = MiddlewareScope::path_subtree("/account/**");
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(mw("orders_pre", MiddlewarePhase::PreRW, -10).with_scope(orders.clone()))
+            .unwrap();
+        registry
+            .register(mw("account_pre", MiddlewarePhase::PreRW, 0).with_scope(account.clone()))
+            .unwrap();
+        registry
+            .register(mw("global_pre", MiddlewarePhase::PreRW, 10))
+            .unwrap();
+        registry
+            .register(mw("orders_post", MiddlewarePhase::PostRO, 10).with_scope(orders.clone()))
+            .unwrap();
+        registry
+            .register(mw("global_post", MiddlewarePhase::PostRO, 0))
+            .unwrap();
+
+        let meta = route("orders.detail", "/orders/:id", &[]);
+        let nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context());
+        assert_eq!(chain.pre_rw_chain, ids(&["global_pre", "orders_pre"]));
+        assert_eq!(chain.post_ro_chain, ids(&["orders_post", "global_post"]));
+        assert_eq!(
+            chain.scope_of(&MiddlewareId("orders_pre".into())),
+            Some(&orders)
+        );
+        assert_eq!(
+            chain.scope_of(&MiddlewareId("global_pre".into())),
+            Some(&MiddlewareScope::Global)
+        );
+
+        let meta = route("account.settings", "/account/settings", &[]);
+        let nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context());
+        assert_eq!(chain.pre_rw_chain, ids(&["global_pre", "account_pre"]));
+
+        assert_eq!(
+            registry.register(
+                mw("early", MiddlewarePhase::PreRW, 0)
+                    .with_scope(account)
+                    .with_runs_before(["global_pre"])
+            ),
+            Err(RegistryError::ScopeConflict {
+                first: MiddlewareId("early".into()),
+                then: MiddlewareId("global_pre".into()),
+            })
+        );
+    }
+}
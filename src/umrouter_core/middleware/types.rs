@@ -1,7 +1,16 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::umrouter_core::route::RouteMeta;
-use crate::umrouter_core::types::{CanonicalParams, MiddlewareId, RuntimeKind, StackId};
+use super::async_executor::MiddlewareExecutor;
+use super::failure::FailurePolicy;
+use super::matchers::{glob_match, path_has_prefix};
+use crate::umrouter_core::pipeline::NavRequest;
+use crate::umrouter_core::route::{RouteMeta, TransitionSpec};
+use crate::umrouter_core::types::{
+    CanonicalParams, MiddlewareId, PresentationMode, RuntimeKind, StackId,
+};
 
 /// 中间件执行阶段。
 ///
@@ -44,6 +53,23 @@ pub enum AccessMode {
     ReadWrite,
 }
 
+/// 超出时间预算时采取的策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BudgetPolicy {
+    /// 照常使用结果，只记录超时。
+    #[default]
+    Continue,
+
+    /// 中止整个导航。
+    Abort,
+
+    /// 跳过该中间件：丢弃它的结果与修改，继续执行后续中间件。
+    ///
+    /// 用作全局预算的策略时含义不同：超出后结束整条链并视为完成，
+    /// 剩余的中间件（包括鉴权等守卫）都不会执行。
+    Skip,
+}
+
 //
 // ========== Matcher 相关 ==========
 //
@@ -64,6 +90,20 @@ pub struct MatchContext<'a> {
     pub params: &'a CanonicalParams,
 }
 
+/// Matcher 的数据依赖范围。
+///
+/// 决定匹配结果能否按路由预先计算并缓存。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatcherDependency {
+    /// 只依赖 RouteMeta（tags / name / path 等）。
+    ///
+    /// 同一条路由的匹配结果恒定，可以按 RouteId 缓存。
+    RouteOnly,
+
+    /// 依赖请求数据（params / runtime / 目标栈等），每次请求都需要重新执行。
+    Request,
+}
+
 /// Matcher trait：决定中间件是否对当前请求生效。
 ///
 /// 不同实现方式（Rust/FFI/WASM/JS）各自实现这个 trait。
@@ -75,6 +115,14 @@ pub trait Matcher: Send + Sync {
     fn name(&self) -> &str {
         "unnamed_matcher"
     }
+
+    /// 可选：声明匹配结果依赖哪些数据。
+    ///
+    /// 默认保守地视为依赖请求数据；
+    /// 只读取 `ctx.route` 的实现应返回 `RouteOnly`，以便按路由缓存中间件链。
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::Request
+    }
 }
 
 //
@@ -93,7 +141,104 @@ pub enum MiddlewareResult {
     Abort { reason: String },
 
     /// 重定向到另一个路由。
-    Redirect { target: String },
+    ///
+    /// 携带完整的导航请求（目标 / 参数 / 栈 / 展示模式），
+    /// pipeline 会以该请求重新开始。
+    Redirect {
+        /// 重定向后的导航请求。
+        request: NavRequest,
+
+        /// 可选：被拦截的原始请求，用于在重定向流程结束后恢复。
+        ///
+        /// 例如登录守卫把用户送去登录页，登录成功后继续原来的导航。
+        continuation: Option<Box<NavRequest>>,
+    },
+
+    /// 改写当前导航的方式（目标栈 / 展示模式 / 动画），然后继续执行后续中间件。
+    ///
+    /// 与 Redirect 不同，pipeline 不会重新开始；改写对后续中间件与状态机可见。
+    Rewrite(NavRewrite),
+}
+
+impl MiddlewareResult {
+    /// 改写当前导航并继续。
+    pub fn rewrite(rewrite: NavRewrite) -> Self {
+        Self::Rewrite(rewrite)
+    }
+
+    /// 重定向到指定请求，不携带续接请求。
+    pub fn redirect(request: NavRequest) -> Self {
+        Self::Redirect {
+            request,
+            continuation: None,
+        }
+    }
+
+    /// 重定向到指定请求，并保存原始请求以便之后恢复。
+    pub fn redirect_then_resume(request: NavRequest, original: NavRequest) -> Self {
+        Self::Redirect {
+            request,
+            continuation: Some(Box::new(original)),
+        }
+    }
+}
+
+/// 对当前导航方式的改写，未设置的字段保持不变。
+///
+/// 同时设置 `transition` 与 `presentation` 时，先整体替换 transition，再覆盖其中的展示模式。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NavRewrite {
+    /// 新的目标栈。
+    pub target_stack: Option<StackId>,
+
+    /// 新的展示模式，例如平板上把 push 改为 sheet。
+    pub presentation: Option<PresentationMode>,
+
+    /// 新的动画与展示配置。
+    pub transition: Option<TransitionSpec>,
+}
+
+impl NavRewrite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 改写目标栈。
+    pub fn with_stack(mut self, stack: StackId) -> Self {
+        self.target_stack = Some(stack);
+        self
+    }
+
+    /// 改写展示模式。
+    pub fn with_presentation(mut self, presentation: PresentationMode) -> Self {
+        self.presentation = Some(presentation);
+        self
+    }
+
+    /// 改写动画与展示配置。
+    pub fn with_transition(mut self, transition: TransitionSpec) -> Self {
+        self.transition = Some(transition);
+        self
+    }
+
+    /// 是否没有任何改写。
+    pub fn is_empty(&self) -> bool {
+        self.target_stack.is_none() && self.presentation.is_none() && self.transition.is_none()
+    }
+
+    /// 合并后一次改写：后者设置的字段覆盖前者。
+    pub fn merge(self, later: NavRewrite) -> Self {
+        let presentation = match (&later.transition, later.presentation) {
+            // 后者整体替换了 transition，前者的展示模式不再生效。
+            (Some(_), None) => None,
+            (_, presentation) => presentation.or(self.presentation),
+        };
+        Self {
+            target_stack: later.target_stack.or(self.target_stack),
+            presentation,
+            transition: later.transition.or(self.transition),
+        }
+    }
 }
 
 /// 执行上下文：Executor 执行时可以访问和修改的信息。
@@ -105,7 +250,10 @@ pub struct ExecuteContext<'a> {
     /// 目标栈。
     pub target_stack: &'a StackId,
 
-    /// 当前请求的 runtime。
+    /// 当前的动画与展示配置（需要修改时返回 `MiddlewareResult::Rewrite`）。
+    pub transition: &'a TransitionSpec,
+
+    /// 当前请求的 runtime（ReadWrite 模式下的修改会写回导航上下文）。
     pub runtime: RuntimeKind,
 
     /// 解析后的参数（可修改，如果是 ReadWrite 模式）。
@@ -115,13 +263,66 @@ pub struct ExecuteContext<'a> {
     pub extensions: &'a mut Extensions,
 }
 
+impl ExecuteContext<'_> {
+    /// 构造 matcher 使用的匹配上下文。
+    pub fn match_context(&self) -> MatchContext<'_> {
+        MatchContext {
+            route: self.route,
+            target_stack: self.target_stack,
+            runtime: self.runtime,
+            params: self.params,
+        }
+    }
+
+    /// 构造只读中间件使用的观察上下文。
+    pub fn observe(&self) -> ObserveContext<'_> {
+        ObserveContext {
+            route: self.route,
+            target_stack: self.target_stack,
+            transition: self.transition,
+            runtime: self.runtime,
+            params: self.params,
+            extensions: self.extensions.view(),
+        }
+    }
+}
+
+/// 观察上下文：只读中间件（如 PostRO 的日志、埋点）可以访问的信息。
+///
+/// 所有字段都不可修改，扩展数据通过 ExtensionsView 读取。
+#[derive(Debug, Clone, Copy)]
+pub struct ObserveContext<'a> {
+    /// 路由元信息。
+    pub route: &'a RouteMeta,
+
+    /// 目标栈。
+    pub target_stack: &'a StackId,
+
+    /// 当前的动画与展示配置。
+    pub transition: &'a TransitionSpec,
+
+    /// 当前请求的 runtime。
+    pub runtime: RuntimeKind,
+
+    /// 解析后的参数。
+    pub params: &'a CanonicalParams,
+
+    /// 扩展数据的只读视图。
+    pub extensions: ExtensionsView<'a>,
+}
+
 /// 扩展数据容器。
 ///
-/// 用于中间件之间传递数据。
-/// TODO: 后续可以替换为类型安全的 TypeMap。
-#[derive(Debug, Default)]
+/// 用于中间件之间传递数据，包含两部分：
+/// - 字符串表：`insert_str` / `get_str`，便于跨语言（FFI/JS/WASM）传递
+/// - 类型表：`insert::<T>` / `get::<T>`，按 TypeId 存放任意 `Send + Sync` 值
+///
+/// Clone 的开销很小：类型表中的值通过 Arc 共享，
+/// 适合给只读中间件提供快照。
+#[derive(Default, Clone)]
 pub struct Extensions {
-    data: std::collections::HashMap<String, String>,
+    data: HashMap<String, String>,
+    typed: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
 }
 
 impl Extensions {
@@ -129,13 +330,100 @@ impl Extensions {
         Self::default()
     }
 
-    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+    /// 写入字符串数据。
+    pub fn insert_str(&mut self, key: impl Into<String>, value: impl Into<String>) {
         self.data.insert(key.into(), value.into());
     }
 
-    pub fn get(&self, key: &str) -> Option<&str> {
+    /// 读取字符串数据。
+    pub fn get_str(&self, key: &str) -> Option<&str> {
         self.data.get(key).map(|s| s.as_str())
     }
+
+    /// 所有字符串数据。
+    pub fn str_entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.data.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// 写入一个类型化的值，同类型的旧值会被替换。
+    ///
+    /// ```
+    /// use umrouter::Extensions;
+    ///
+    /// struct Session {
+    ///     user_id: u64,
+    /// }
+    ///
+    /// let mut ext = Extensions::new();
+    /// ext.insert(Session { user_id: 7 });
+    /// assert_eq!(ext.get::<Session>().map(|s| s.user_id), Some(7));
+    /// ```
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.typed.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// 读取一个类型化的值。
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.typed
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// 是否存在某个类型的值。
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.typed.contains_key(&TypeId::of::<T>())
+    }
+
+    /// 移除某个类型的值。
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Arc<T>> {
+        self.typed
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast::<T>().ok())
+    }
+
+    /// 获取只读视图。
+    pub fn view(&self) -> ExtensionsView<'_> {
+        ExtensionsView { inner: self }
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("data", &self.data)
+            .field("typed_count", &self.typed.len())
+            .finish()
+    }
+}
+
+/// Extensions 的只读视图。
+///
+/// 通过 `ObserveContext` 提供给 PostRO 等只读中间件：只能读取，不能写入。
+#[derive(Debug, Clone, Copy)]
+pub struct ExtensionsView<'a> {
+    inner: &'a Extensions,
+}
+
+impl<'a> ExtensionsView<'a> {
+    /// 读取字符串数据。
+    pub fn get_str(&self, key: &str) -> Option<&'a str> {
+        self.inner.get_str(key)
+    }
+
+    /// 所有字符串数据。
+    pub fn str_entries(&self) -> impl Iterator<Item = (&'a str, &'a str)> + use<'a> {
+        self.inner.str_entries()
+    }
+
+    /// 读取一个类型化的值。
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&'a T> {
+        self.inner.get::<T>()
+    }
+
+    /// 是否存在某个类型的值。
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.inner.contains::<T>()
+    }
 }
 
 /// Executor trait：中间件的具体执行逻辑。
@@ -155,6 +443,52 @@ pub trait Executor: Send + Sync {
 // ========== Middleware 定义 ==========
 //
 
+/// 中间件作用域。
+///
+/// 作用域内的路由才会执行该中间件的 matcher。
+/// PreRW 阶段作用域中间件在全局中间件之后执行，PostRO 阶段则在全局中间件之前执行。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum MiddlewareScope {
+    /// 全局：对所有路由生效。
+    #[default]
+    Global,
+
+    /// 路由命名空间：按路由名 glob 匹配，例如 "orders.*"。
+    Namespace(String),
+
+    /// 路径子树：例如 "/account/**"，匹配 "/account" 及其下所有路径。
+    PathSubtree(String),
+}
+
+impl MiddlewareScope {
+    /// 路由命名空间作用域。
+    pub fn namespace(pattern: impl Into<String>) -> Self {
+        Self::Namespace(pattern.into())
+    }
+
+    /// 路径子树作用域，末尾的 "/**" 可省略。
+    pub fn path_subtree(prefix: impl Into<String>) -> Self {
+        Self::PathSubtree(prefix.into())
+    }
+
+    /// 是否为全局作用域。
+    pub fn is_global(&self) -> bool {
+        matches!(self, Self::Global)
+    }
+
+    /// 路由是否在作用域内。
+    pub fn contains(&self, route: &RouteMeta) -> bool {
+        match self {
+            Self::Global => true,
+            Self::Namespace(pattern) => glob_match(pattern, &route.name),
+            Self::PathSubtree(prefix) => {
+                let prefix = prefix.strip_suffix("/**").unwrap_or(prefix);
+                path_has_prefix(&route.path, prefix)
+            }
+        }
+    }
+}
+
 /// 完整的中间件定义。
 ///
 /// 一个中间件由以下部分组成：
@@ -162,6 +496,7 @@ pub trait Executor: Send + Sync {
 /// - Executor：具体的执行逻辑
 /// - Phase：在 pipeline 的哪个阶段执行
 /// - Priority：同阶段内的执行顺序
+/// - Scope：全局或绑定到路由命名空间 / 路径子树
 pub struct Middleware {
     /// 中间件唯一标识。
     pub id: MiddlewareId,
@@ -169,8 +504,8 @@ pub struct Middleware {
     /// 匹配器：决定这个中间件是否对当前请求生效。
     pub matcher: Arc<dyn Matcher>,
 
-    /// 执行器：具体的中间件逻辑。
-    pub executor: Arc<dyn Executor>,
+    /// 执行器：具体的中间件逻辑（同步或异步）。
+    pub executor: MiddlewareExecutor,
 
     /// 执行阶段：PreRW / Core / PostRO。
     pub phase: MiddlewarePhase,
@@ -181,10 +516,122 @@ pub struct Middleware {
     /// 优先级：同阶段内的执行顺序。
     ///
     /// 数值越小优先级越高，越先执行。
+    /// 与 `runs_after` / `runs_before` 同时存在时，先满足顺序约束。
     pub priority: i32,
 
+    /// 必须在这些中间件之后执行（仅对同阶段的中间件生效）。
+    pub runs_after: HashSet<MiddlewareId>,
+
+    /// 必须在这些中间件之前执行（仅对同阶段的中间件生效）。
+    pub runs_before: HashSet<MiddlewareId>,
+
+    /// 作用域：全局，或绑定到某个路由命名空间 / 路径子树。
+    pub scope: MiddlewareScope,
+
     /// 业务标签（用于分类、调试等）。
     pub tags: Vec<String>,
+
+    /// 可选：单次执行的时间预算。
+    pub budget: Option<Duration>,
+
+    /// 超出时间预算时的策略。
+    pub budget_policy: BudgetPolicy,
+
+    /// matcher / executor panic 时的策略：fail-open 或 fail-closed。
+    pub failure_policy: FailurePolicy,
+}
+
+impl Middleware {
+    /// 创建中间件，其余字段取默认值：
+    /// 读写模式、priority 为 0、无标签、无时间预算、fail-open。
+    pub fn new(
+        id: MiddlewareId,
+        matcher: Arc<dyn Matcher>,
+        executor: MiddlewareExecutor,
+        phase: MiddlewarePhase,
+    ) -> Self {
+        Self {
+            id,
+            matcher,
+            executor,
+            phase,
+            access_mode: AccessMode::ReadWrite,
+            priority: 0,
+            runs_after: HashSet::new(),
+            runs_before: HashSet::new(),
+            scope: MiddlewareScope::Global,
+            tags: Vec::new(),
+            budget: None,
+            budget_policy: BudgetPolicy::default(),
+            failure_policy: FailurePolicy::default(),
+        }
+    }
+
+    /// 实际执行时是否只读：PostRO 阶段的中间件无论声明的访问模式如何都按只读执行。
+    pub fn is_read_only(&self) -> bool {
+        self.access_mode == AccessMode::ReadOnly || self.phase == MiddlewarePhase::PostRO
+    }
+
+    /// 设置访问模式。
+    pub fn with_access_mode(mut self, access_mode: AccessMode) -> Self {
+        self.access_mode = access_mode;
+        self
+    }
+
+    /// 设置优先级。
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 声明必须在这些中间件之后执行。
+    pub fn with_runs_after<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runs_after = ids.into_iter().map(|id| MiddlewareId(id.into())).collect();
+        self
+    }
+
+    /// 声明必须在这些中间件之前执行。
+    pub fn with_runs_before<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runs_before = ids.into_iter().map(|id| MiddlewareId(id.into())).collect();
+        self
+    }
+
+    /// 设置作用域。
+    pub fn with_scope(mut self, scope: MiddlewareScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// 设置业务标签。
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置时间预算及超时策略。
+    pub fn with_budget(mut self, budget: Duration, policy: BudgetPolicy) -> Self {
+        self.budget = Some(budget);
+        self.budget_policy = policy;
+        self
+    }
+
+    /// 设置 panic 时的失败策略。
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
 }
 
 impl std::fmt::Debug for Middleware {
@@ -196,7 +643,13 @@ impl std::fmt::Debug for Middleware {
             .field("phase", &self.phase)
             .field("access_mode", &self.access_mode)
             .field("priority", &self.priority)
+            .field("runs_after", &self.runs_after)
+            .field("runs_before", &self.runs_before)
+            .field("scope", &self.scope)
             .field("tags", &self.tags)
+            .field("budget", &self.budget)
+            .field("budget_policy", &self.budget_policy)
+            .field("failure_policy", &self.failure_policy)
             .finish()
     }
 }
@@ -206,11 +659,17 @@ impl Clone for Middleware {
         Self {
             id: self.id.clone(),
             matcher: Arc::clone(&self.matcher),
-            executor: Arc::clone(&self.executor),
+            executor: self.executor.clone(),
             phase: self.phase,
             access_mode: self.access_mode,
             priority: self.priority,
+            runs_after: self.runs_after.clone(),
+            runs_before: self.runs_before.clone(),
+            scope: self.scope.clone(),
             tags: self.tags.clone(),
+            budget: self.budget,
+            budget_policy: self.budget_policy,
+            failure_policy: self.failure_policy,
         }
     }
 }
@@ -223,6 +682,7 @@ impl Clone for Middleware {
 pub struct FnMatcher<F> {
     name: String,
     func: F,
+    dependency: MatcherDependency,
 }
 
 impl<F> FnMatcher<F>
@@ -233,8 +693,15 @@ where
         Self {
             name: name.into(),
             func,
+            dependency: MatcherDependency::Request,
         }
     }
+
+    /// 声明闭包只读取 `ctx.route`，匹配结果可以按路由缓存。
+    pub fn route_only(mut self) -> Self {
+        self.dependency = MatcherDependency::RouteOnly;
+        self
+    }
 }
 
 impl<F> Matcher for FnMatcher<F>
@@ -248,6 +715,10 @@ where
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn dependency(&self) -> MatcherDependency {
+        self.dependency
+    }
 }
 
 /// 用闭包创建 Executor 的便捷结构。
@@ -281,6 +752,38 @@ where
     }
 }
 
+/// 基于闭包的只读 Executor：闭包只能拿到 ObserveContext，结果总是 Continue。
+pub struct FnObserver<F> {
+    name: String,
+    func: F,
+}
+
+impl<F> FnObserver<F>
+where
+    F: Fn(&ObserveContext) + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, func: F) -> Self {
+        Self {
+            name: name.into(),
+            func,
+        }
+    }
+}
+
+impl<F> Executor for FnObserver<F>
+where
+    F: Fn(&ObserveContext) + Send + Sync,
+{
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        (self.func)(&ctx.observe());
+        MiddlewareResult::Continue
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 //
 // ========== 匹配所有的 Matcher ==========
 //
@@ -296,4 +799,8 @@ impl Matcher for AlwaysMatcher {
     fn name(&self) -> &str {
         "always"
     }
+
+    fn dependency(&self) -> MatcherDependency {
+        MatcherDependency::RouteOnly
+    }
 }
@@ -0,0 +1,410 @@
+//! WASM 沙箱：加载合作方远程下发的中间件插件。
+//!
+//! 插件（guest）ABI：
+//! - 导出 `memory`：线性内存
+//! - 导出 `alloc(len: i32) -> i32`：在 guest 内存中分配 `len` 字节，返回起始偏移
+//! - 导出 `execute(ptr: i32, len: i32) -> i64`：输入为执行上下文 JSON
+//!   （含 RouteMeta、CanonicalParams 与 Extensions 字符串表，格式见 `json` 模块），
+//!   返回 `(result_ptr << 32) | result_len` 指向 guest 内存中的执行结果 JSON；返回 0 视为 Continue
+//! - 可选导出 `matches(ptr: i32, len: i32) -> i32`：输入为匹配上下文 JSON，非 0 表示匹配；
+//!   未导出时对所有路由生效
+//!
+//! 插件不能导入任何宿主函数；每次调用都在全新的实例中执行，
+//! 指令数受 fuel 约束、线性内存受 `WasmLimits::memory_bytes` 约束。
+//! 超限、trap 或返回无法解析的结果时视为中间件 panic，按该中间件的 FailurePolicy 处理。
+
+use std::sync::Arc;
+
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+use super::json;
+use crate::umrouter_core::middleware::{
+    AlwaysMatcher, ExecuteContext, Executor, MatchContext, Matcher, Middleware, MiddlewareExecutor,
+    MiddlewarePhase, MiddlewareRegistry, MiddlewareResult, RegistryError,
+};
+use crate::umrouter_core::types::MiddlewareId;
+
+/// 单次插件调用的资源上限。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// 每次调用（含实例化与 `alloc`）可消耗的 fuel，约等于执行的指令数。
+    pub fuel: u64,
+
+    /// 线性内存上限（字节）。
+    pub memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// 插件加载 / 执行错误。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmPluginError {
+    /// 插件名称。
+    pub plugin: String,
+
+    /// 错误信息。
+    pub message: String,
+}
+
+impl std::fmt::Display for WasmPluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "wasm plugin `{}` failed: {}", self.plugin, self.message)
+    }
+}
+
+impl std::error::Error for WasmPluginError {}
+
+/// 已编译并校验过 ABI 的插件模块。
+#[derive(Clone)]
+pub struct WasmPlugin {
+    name: Arc<str>,
+    engine: Engine,
+    module: Arc<Module>,
+    limits: WasmLimits,
+    has_matcher: bool,
+}
+
+impl WasmPlugin {
+    /// 编译插件并校验导出（wasm 二进制）。
+    pub fn load(name: impl Into<String>, bytes: &[u8]) -> Result<Self, WasmPluginError> {
+        Self::load_with_limits(name, bytes, WasmLimits::default())
+    }
+
+    /// 以指定资源上限编译插件并校验导出。
+    pub fn load_with_limits(
+        name: impl Into<String>,
+        bytes: &[u8],
+        limits: WasmLimits,
+    ) -> Result<Self, WasmPluginError> {
+        let name: Arc<str> = name.into().into();
+        let error = |message: String| WasmPluginError {
+            plugin: name.to_string(),
+            message,
+        };
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| error(e.to_string()))?;
+        let mut plugin = Self {
+            name: name.clone(),
+            engine,
+            module: Arc::new(module),
+            limits,
+            has_matcher: false,
+        };
+
+        let mut store = plugin.store()?;
+        let guest = plugin.instantiate(&mut store)?;
+        plugin.has_matcher = guest
+            .instance
+            .get_typed_func::<(i32, i32), i32>(&store, "matches")
+            .is_ok();
+        guest
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&store, "execute")
+            .map_err(|e| error(format!("invalid `execute` export: {e}")))?;
+        Ok(plugin)
+    }
+
+    /// 插件名称。
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 资源上限。
+    pub fn limits(&self) -> WasmLimits {
+        self.limits
+    }
+
+    /// 插件导出的 `matches`；未导出时返回 None。
+    pub fn matcher(&self) -> Option<WasmMatcher> {
+        self.has_matcher.then(|| WasmMatcher {
+            plugin: self.clone(),
+        })
+    }
+
+    /// 插件导出的 `execute`。
+    pub fn executor(&self) -> WasmExecutor {
+        WasmExecutor {
+            plugin: self.clone(),
+        }
+    }
+
+    /// 生成中间件：有 `matches` 导出时以其匹配，否则对所有路由生效。
+    pub fn middleware(&self, id: MiddlewareId, phase: MiddlewarePhase) -> Middleware {
+        let matcher: Arc<dyn Matcher> = match self.matcher() {
+            Some(matcher) => Arc::new(matcher),
+            None => Arc::new(AlwaysMatcher),
+        };
+        Middleware::new(
+            id,
+            matcher,
+            MiddlewareExecutor::sync(self.executor()),
+            phase,
+        )
+    }
+
+    /// 以 `id` 将插件注册到注册表。
+    pub fn register(
+        &self,
+        registry: &mut MiddlewareRegistry,
+        id: MiddlewareId,
+        phase: MiddlewarePhase,
+    ) -> Result<(), RegistryError> {
+        registry.register(self.middleware(id, phase))
+    }
+
+    /// 在全新实例中调用 `entry`，返回 guest 的返回值。
+    fn call<R: wasmi::WasmResults>(
+        &self,
+        entry: &str,
+        input: &[u8],
+    ) -> Result<(Store<StoreLimits>, Guest, R), WasmPluginError> {
+        let mut store = self.store()?;
+        let guest = self.instantiate(&mut store)?;
+        let len = i32::try_from(input.len())
+            .map_err(|_| self.error(format!("input of {} bytes is too large", input.len())))?;
+        let ptr = guest
+            .alloc
+            .call(&mut store, len)
+            .map_err(|e| self.error(format!("`alloc` failed: {e}")))?;
+        guest
+            .memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| self.error(format!("`alloc` returned an invalid pointer: {e}")))?;
+
+        let func: TypedFunc<(i32, i32), R> = guest
+            .instance
+            .get_typed_func(&store, entry)
+            .map_err(|e| self.error(format!("invalid `{entry}` export: {e}")))?;
+        let output = func
+            .call(&mut store, (ptr, len))
+            .map_err(|e| self.error(format!("`{entry}` failed: {e}")))?;
+        Ok((store, guest, output))
+    }
+
+    fn store(&self) -> Result<Store<StoreLimits>, WasmPluginError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(self.limits.fuel)
+            .map_err(|e| self.error(e.to_string()))?;
+        Ok(store)
+    }
+
+    fn instantiate(&self, store: &mut Store<StoreLimits>) -> Result<Guest, WasmPluginError> {
+        // 空 Linker：插件无法导入任何宿主函数。
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut *store, &self.module)
+            .and_then(|pre| pre.start(&mut *store))
+            .map_err(|e| self.error(format!("instantiation failed: {e}")))?;
+        let memory = instance
+            .get_memory(&*store, "memory")
+            .ok_or_else(|| self.error("missing `memory` export".into()))?;
+        let alloc = instance
+            .get_typed_func(&*store, "alloc")
+            .map_err(|e| self.error(format!("invalid `alloc` export: {e}")))?;
+        Ok(Guest {
+            instance,
+            memory,
+            alloc,
+        })
+    }
+
+    fn error(&self, message: String) -> WasmPluginError {
+        WasmPluginError {
+            plugin: self.name.to_string(),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name)
+            .field("limits", &self.limits)
+            .field("has_matcher", &self.has_matcher)
+            .finish()
+    }
+}
+
+/// 单次调用中的插件实例。
+struct Guest {
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+/// 以插件 `matches` 导出实现的 Matcher。
+#[derive(Debug, Clone)]
+pub struct WasmMatcher {
+    plugin: WasmPlugin,
+}
+
+impl Matcher for WasmMatcher {
+    fn matches(&self, ctx: &MatchContext) -> bool {
+        let input = json::match_context(ctx).to_string();
+        match self.plugin.call::<i32>("matches", input.as_bytes()) {
+            Ok((_, _, matched)) => matched != 0,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.plugin.name()
+    }
+}
+
+/// 以插件 `execute` 导出实现的 Executor。
+#[derive(Debug, Clone)]
+pub struct WasmExecutor {
+    plugin: WasmPlugin,
+}
+
+impl Executor for WasmExecutor {
+    fn execute(&self, ctx: &mut ExecuteContext) -> MiddlewareResult {
+        let input = json::execute_context(ctx).to_string();
+        let (store, guest, packed) = self
+            .plugin
+            .call::<i64>("execute", input.as_bytes())
+            .unwrap_or_else(|e| panic!("{e}"));
+        if packed == 0 {
+            return MiddlewareResult::Continue;
+        }
+
+        let packed = packed as u64;
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let output = guest
+            .memory
+            .data(&store)
+            .get(ptr..ptr.saturating_add(len))
+            .unwrap_or_else(|| {
+                panic!(
+                    "{}",
+                    self.plugin
+                        .error(format!("result {ptr}+{len} is out of bounds"))
+                )
+            });
+        let output = String::from_utf8_lossy(output);
+        json::apply_execute_output(ctx, &output)
+            .unwrap_or_else(|e| panic!("{}", self.plugin.error(format!("invalid result: {e}"))))
+    }
+
+    fn name(&self) -> &str {
+        self.plugin.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::middleware::FailurePolicy;
+    use crate::umrouter_core::pipeline::PipelineResult;
+    use crate::umrouter_core::test_support::{route, run_pre_rw};
+    use crate::umrouter_core::types::CanonicalParams;
+
+    /// 带 "vip" 标签的路由被中止；`spin` 参数触发死循环。
+    const VIP_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "\"vip\"")
+          (data (i32.const 16) "spin")
+          (data (i32.const 32) "{\"result\":\"abort\",\"reason\":\"vip only\"}")
+
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+
+          ;; 在 [ptr, ptr+len) 中查找偏移 $needle 处长度为 $n 的字节串。
+          (func $contains (param $ptr i32) (param $len i32) (param $needle i32) (param $n i32)
+                          (result i32)
+            (local $i i32) (local $j i32)
+            (block $not_found
+              (loop $outer
+                (br_if $not_found (i32.gt_s (local.get $n)
+                                            (i32.sub (local.get $len) (local.get $i))))
+                (local.set $j (i32.const 0))
+                (block $mismatch
+                  (loop $inner
+                    (if (i32.eq (local.get $j) (local.get $n)) (then (return (i32.const 1))))
+                    (br_if $mismatch
+                      (i32.ne
+                        (i32.load8_u (i32.add (i32.add (local.get $ptr) (local.get $i))
+                                              (local.get $j)))
+                        (i32.load8_u (i32.add (local.get $needle) (local.get $j)))))
+                    (local.set $j (i32.add (local.get $j) (i32.const 1)))
+                    (br $inner)))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $outer)))
+            (i32.const 0))
+
+          (func (export "matches") (param $ptr i32) (param $len i32) (result i32)
+            (call $contains (local.get $ptr) (local.get $len) (i32.const 0) (i32.const 5)))
+
+          (func (export "execute") (param $ptr i32) (param $len i32) (result i64)
+            (if (call $contains (local.get $ptr) (local.get $len) (i32.const 16) (i32.const 4))
+              (then (loop $forever (br $forever))))
+            (i64.or (i64.shl (i64.const 32) (i64.const 32)) (i64.const 38))))
+    "#;
+
+    #[test]
+    fn runs_plugin_within_fuel_limit() {
+        let bytes = wat::parse_str(VIP_PLUGIN).unwrap();
+        assert!(WasmPlugin::load("broken", b"\0asm").is_err());
+
+        let plugin = WasmPlugin::load_with_limits(
+            "vip_plugin",
+            &bytes,
+            WasmLimits {
+                fuel: 100_000,
+                ..WasmLimits::default()
+            },
+        )
+        .unwrap();
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(
+                plugin
+                    .middleware(MiddlewareId("vip".into()), MiddlewarePhase::PreRW)
+                    .with_failure_policy(FailurePolicy::FailClosed),
+            )
+            .unwrap();
+
+        let run = |tags: &[&str], mode: &str| {
+            let mut params = CanonicalParams::default();
+            params.map.insert("mode".into(), mode.into());
+            run_pre_rw(&registry, &route("vip.lounge", "/vip/lounge", tags), params).0
+        };
+
+        assert!(matches!(run(&[], "walk"), PipelineResult::Completed));
+        let PipelineResult::Aborted { by, reason } = run(&["vip"], "walk") else {
+            panic!("expected vip plugin to abort");
+        };
+        assert_eq!((by.0.as_str(), reason.as_str()), ("vip", "vip only"));
+
+        // 死循环耗尽 fuel，FailClosed 时阻断导航。
+        let PipelineResult::Failed(failure) = run(&["vip"], "spin") else {
+            panic!("expected fuel exhaustion");
+        };
+        assert!(failure.message.contains("fuel"), "{}", failure.message);
+    }
+}
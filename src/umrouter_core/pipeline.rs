@@ -1,6 +1,11 @@
+mod cache;
 mod chain;
 mod context;
+mod request;
+mod runner;
 
+pub use cache::*;
 pub use chain::*;
 pub use context::*;
-
+pub use request::*;
+pub use runner::*;
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use super::chain::ResolvedMiddlewareChain;
+use crate::umrouter_core::middleware::{
+    MatchContext, MatcherDependency, MiddlewareFailure, MiddlewarePhase, MiddlewareRegistry,
+};
+use crate::umrouter_core::route::RouteStore;
+use crate::umrouter_core::types::{MiddlewareId, RouteId};
+
+/// 按路由预计算后的候选中间件。
+#[derive(Debug, Clone)]
+enum Candidate {
+    /// matcher 只依赖路由且已匹配，直接进入链。
+    Matched(MiddlewareId),
+
+    /// matcher 依赖请求数据，每次请求都需要重新执行。
+    Deferred(MiddlewareId),
+}
+
+/// 某条路由预计算得到的候选链（已按执行顺序排好）。
+#[derive(Debug, Clone, Default)]
+struct RouteCandidates {
+    pre_rw: Vec<Candidate>,
+    core: Vec<Candidate>,
+    post_ro: Vec<Candidate>,
+}
+
+/// 中间件链缓存。
+///
+/// 对每个 RouteId 预先检查作用域，并执行一次所有 `RouteOnly` matcher：
+/// - 作用域不包含该路由、或未匹配的中间件直接剔除
+/// - 已匹配的中间件直接进入链
+/// - 依赖请求数据的中间件保留位置，按请求执行 matcher
+///
+/// 预计算使用路由表中该 RouteId 的 RouteMeta，而不是请求携带的路由；
+/// 路由表中不存在的 RouteId 不缓存，直接完整解析。
+/// 注册表或路由表版本号变化时，缓存整体失效。
+#[derive(Debug, Default)]
+pub struct ChainCache {
+    registry_version: u64,
+    store_version: u64,
+    routes: HashMap<RouteId, RouteCandidates>,
+}
+
+impl ChainCache {
+    /// 创建空缓存。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 解析当前请求对应的中间件链，必要时重建该路由的缓存。
+    pub fn resolve(
+        &mut self,
+        registry: &MiddlewareRegistry,
+        store: &RouteStore,
+        ctx: &MatchContext,
+    ) -> ResolvedMiddlewareChain {
+        if self.registry_version != registry.version() || self.store_version != store.version() {
+            self.routes.clear();
+            self.registry_version = registry.version();
+            self.store_version = store.version();
+        }
+
+        let Some(route) = store.get(ctx.route.id) else {
+            return registry.resolve_chain(ctx);
+        };
+        let ctx = &MatchContext { route, ..*ctx };
+        // 本次预计算中 panic 的 matcher：失败直接计入本次结果，不再执行第二次。
+        let mut precomputed = Vec::new();
+        let candidates = self
+            .routes
+            .entry(route.id)
+            .or_insert_with(|| RouteCandidates {
+                pre_rw: precompute(registry, MiddlewarePhase::PreRW, ctx, &mut precomputed),
+                core: precompute(registry, MiddlewarePhase::Core, ctx, &mut precomputed),
+                post_ro: precompute(registry, MiddlewarePhase::PostRO, ctx, &mut precomputed),
+            });
+
+        let mut failures = Vec::new();
+        let mut finish = |candidates: &[Candidate]| {
+            finish(registry, candidates, ctx, &precomputed, &mut failures)
+        };
+        let mut chain = ResolvedMiddlewareChain {
+            pre_rw_chain: finish(&candidates.pre_rw),
+            core_chain: finish(&candidates.core),
+            post_ro_chain: finish(&candidates.post_ro),
+            failures,
+            ..Default::default()
+        };
+        chain.record_scopes(registry);
+        chain
+    }
+
+    /// 已缓存的路由数量。
+    pub fn cached_routes(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// 手动清空缓存。
+    pub fn clear(&mut self) {
+        self.routes.clear();
+    }
+}
+
+fn precompute(
+    registry: &MiddlewareRegistry,
+    phase: MiddlewarePhase,
+    ctx: &MatchContext,
+    failures: &mut Vec<MiddlewareFailure>,
+) -> Vec<Candidate> {
+    registry
+        .ordered_by_phase(phase)
+        .into_iter()
+        .filter(|m| m.scope.contains(ctx.route))
+        .filter_map(|m| match m.matcher.dependency() {
+            MatcherDependency::RouteOnly => match registry.evaluate(m, ctx) {
+                Ok(true) => Some(Candidate::Matched(m.id.clone())),
+                Ok(false) => None,
+                // panic 的 matcher 不缓存结果，之后按请求重试（并继续累计熔断次数）。
+                Err(failure) => {
+                    failures.push(failure);
+                    Some(Candidate::Deferred(m.id.clone()))
+                }
+            },
+            MatcherDependency::Request => Some(Candidate::Deferred(m.id.clone())),
+        })
+        .collect()
+}
+
+fn finish(
+    registry: &MiddlewareRegistry,
+    candidates: &[Candidate],
+    ctx: &MatchContext,
+    precomputed: &[MiddlewareFailure],
+    failures: &mut Vec<MiddlewareFailure>,
+) -> Vec<MiddlewareId> {
+    candidates
+        .iter()
+        .filter_map(|candidate| match candidate {
+            Candidate::Matched(id) => Some(id.clone()),
+            Candidate::Deferred(id) => {
+                if let Some(failure) = precomputed.iter().find(|f| f.id == *id) {
+                    failures.push(failure.clone());
+                    return None;
+                }
+                let middleware = registry.get(id)?;
+                match registry.evaluate(middleware, ctx) {
+                    Ok(matched) => matched.then(|| id.clone()),
+                    Err(failure) => {
+                        failures.push(failure);
+                        None
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::umrouter_core::middleware::{FnMatcher, NameGlobMatcher, ParamMatcher, TagMatcher};
+    use crate::umrouter_core::test_support::{middleware, noop, route};
+    use crate::umrouter_core::types::CanonicalParams;
+
+    #[test]
+    fn route_only_matchers_run_once_per_route() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let counting = FnMatcher::new("counting", move |ctx: &MatchContext| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ctx.route.tags.iter().any(|t| t == "auth-required")
+        })
+        .route_only();
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(middleware(
+                "auth",
+                MiddlewarePhase::PreRW,
+                10,
+                counting,
+                noop(),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "orders_log",
+                MiddlewarePhase::PostRO,
+                0,
+                NameGlobMatcher::new("orders.*"),
+                noop(),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "debug",
+                MiddlewarePhase::PreRW,
+                0,
+                ParamMatcher::present("debug"),
+                noop(),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "admin",
+                MiddlewarePhase::PreRW,
+                5,
+                TagMatcher::new("admin-only"),
+                noop(),
+            ))
+            .unwrap();
+
+        let mut store = RouteStore::new();
+        store
+            .insert(route("orders.detail", "/orders/:id", &["auth-required"]))
+            .unwrap();
+        let meta = store.get(RouteId(0)).unwrap().clone();
+
+        let mut cache = ChainCache::new();
+        let mut params = CanonicalParams::default();
+        for _ in 0..3 {
+            let ctx = MatchContext {
+                route: &meta,
+                target_stack: &meta.preferred_stack,
+                runtime: meta.runtime,
+                params: &params,
+            };
+            let chain = cache.resolve(&registry, &store, &ctx);
+            assert_eq!(chain.pre_rw_chain, vec![MiddlewareId("auth".into())]);
+            assert_eq!(chain.post_ro_chain, vec![MiddlewareId("orders_log".into())]);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 预计算只认路由表中的 RouteMeta，请求携带的同 id 路由不影响缓存。
+        let mut forged = meta.clone();
+        forged.tags.clear();
+        forged.name = "home.index".into();
+        let ctx = MatchContext {
+            route: &forged,
+            target_stack: &meta.preferred_stack,
+            runtime: meta.runtime,
+            params: &params,
+        };
+        let chain = ChainCache::new().resolve(&registry, &store, &ctx);
+        assert_eq!(chain.pre_rw_chain, vec![MiddlewareId("auth".into())]);
+        assert_eq!(chain.post_ro_chain, vec![MiddlewareId("orders_log".into())]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 依赖参数的 matcher 每次请求都会执行。
+        params.map.insert("debug".into(), true.into());
+        let ctx = MatchContext {
+            route: &meta,
+            target_stack: &meta.preferred_stack,
+            runtime: meta.runtime,
+            params: &params,
+        };
+        let chain = cache.resolve(&registry, &store, &ctx);
+        assert_eq!(
+            chain.pre_rw_chain,
+            vec![MiddlewareId("debug".into()), MiddlewareId("auth".into())]
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 注册表变更后缓存失效。
+        registry
+            .register(middleware(
+                "trace",
+                MiddlewarePhase::PostRO,
+                1,
+                NameGlobMatcher::new("*"),
+                noop(),
+            ))
+            .unwrap();
+        let chain = cache.resolve(&registry, &store, &ctx);
+        assert_eq!(chain.post_ro_chain.len(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // 预计算时 panic 的 matcher 只执行一次，失败计入本次结果；之后按请求重试。
+        let id = MiddlewareId("flaky".into());
+        registry
+            .register(middleware(
+                "flaky",
+                MiddlewarePhase::PreRW,
+                1,
+                FnMatcher::new("flaky", |_: &MatchContext| panic!("boom")).route_only(),
+                noop(),
+            ))
+            .unwrap();
+        let chain = cache.resolve(&registry, &store, &ctx);
+        assert_eq!(registry.panic_count(&id), 1);
+        assert_eq!(chain.failures.len(), 1);
+        assert_eq!(chain.failures[0].id, id);
+        let chain = cache.resolve(&registry, &store, &ctx);
+        assert_eq!(registry.panic_count(&id), 2);
+        assert_eq!(chain.failures.len(), 1);
+    }
+}
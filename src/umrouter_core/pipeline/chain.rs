@@ -1,14 +1,17 @@
+use std::collections::HashMap;
+
+use crate::umrouter_core::middleware::{MiddlewareFailure, MiddlewareRegistry, MiddlewareScope};
 use crate::umrouter_core::types::MiddlewareId;
 
 /// 某一次路由解析之后，对应的一条"中间件链配置"。
 ///
 /// 通过遍历所有中间件的 matcher，收集匹配的中间件，
-/// 按 phase 分组并按 priority 排序后得到。
+/// 按 phase 分组并按执行顺序排序后得到。
 ///
-/// - pre_rw_chain：在核心中间件之前执行（业务读写）
+/// - pre_rw_chain：在核心中间件之前执行（业务读写，先全局后作用域）
 /// - core_chain：核心中间件（参数校验 / hook）
-/// - post_ro_chain：在核心之后执行（业务只读）
-#[derive(Debug, Clone)]
+/// - post_ro_chain：在核心之后执行（业务只读，先作用域后全局）
+#[derive(Debug, Clone, Default)]
 pub struct ResolvedMiddlewareChain {
     /// 前置读写中间件 id 链（按 priority 排序）。
     pub pre_rw_chain: Vec<MiddlewareId>,
@@ -18,4 +21,37 @@ pub struct ResolvedMiddlewareChain {
 
     /// 后置只读中间件 id 链（按 priority 排序）。
     pub post_ro_chain: Vec<MiddlewareId>,
+
+    /// 解析过程中 panic 的 matcher。
+    ///
+    /// fail-open 的中间件已从链中剔除；
+    /// 存在 fail-closed 的失败时，调用方应中止导航。
+    pub failures: Vec<MiddlewareFailure>,
+
+    /// 链中每个中间件是由哪个作用域引入的。
+    pub scopes: HashMap<MiddlewareId, MiddlewareScope>,
+}
+
+impl ResolvedMiddlewareChain {
+    /// 第一个要求中止导航的失败（fail-closed）。
+    pub fn blocking_failure(&self) -> Option<&MiddlewareFailure> {
+        self.failures.iter().find(|f| f.is_blocking())
+    }
+
+    /// 引入该中间件的作用域。
+    pub fn scope_of(&self, id: &MiddlewareId) -> Option<&MiddlewareScope> {
+        self.scopes.get(id)
+    }
+
+    /// 根据注册表记录链中每个中间件的作用域。
+    pub(crate) fn record_scopes(&mut self, registry: &MiddlewareRegistry) {
+        let ids = self
+            .pre_rw_chain
+            .iter()
+            .chain(&self.core_chain)
+            .chain(&self.post_ro_chain);
+        self.scopes = ids
+            .filter_map(|id| registry.get(id).map(|m| (id.clone(), m.scope.clone())))
+            .collect();
+    }
 }
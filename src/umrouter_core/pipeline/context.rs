@@ -1,7 +1,8 @@
 use super::chain::ResolvedMiddlewareChain;
-use crate::umrouter_core::middleware::Extensions;
-use crate::umrouter_core::route::ResolvedRoute;
-use crate::umrouter_core::types::CanonicalParams;
+use super::request::NavRequest;
+use crate::umrouter_core::middleware::{Extensions, MatchContext, NavRewrite};
+use crate::umrouter_core::route::{ResolvedRoute, RouteMeta, TransitionSpec};
+use crate::umrouter_core::types::{CanonicalParams, RuntimeKind, StackId};
 
 /// 一次导航解析完成后，进入 pipeline 之前的"完整上下文描述"。
 ///
@@ -24,3 +25,80 @@ pub struct NavResolution<'a> {
     /// 扩展数据容器（中间件之间传递数据）。
     pub extensions: Extensions,
 }
+
+/// 一次导航在中间件 pipeline 中流转的可变状态。
+///
+/// runner 每执行一个中间件，都会基于它构造一个 ExecuteContext；
+/// 读写中间件对参数和扩展数据的修改、以及返回的 Rewrite 都会写回这里，
+/// 对后续中间件与状态机可见。
+#[derive(Debug)]
+pub struct NavigationContext<'a> {
+    /// 目标路由元信息。
+    pub route: &'a RouteMeta,
+
+    /// 目标栈。
+    pub target_stack: StackId,
+
+    /// 动画与展示配置（包含展示模式）。
+    pub transition: TransitionSpec,
+
+    /// 当前请求的 runtime。
+    pub runtime: RuntimeKind,
+
+    /// 规范化参数。
+    pub params: CanonicalParams,
+
+    /// 扩展数据容器（中间件之间传递数据）。
+    pub extensions: Extensions,
+}
+
+impl<'a> NavigationContext<'a> {
+    /// 以路由默认配置创建上下文：
+    /// 目标栈取 preferred_stack，动画取 transition_spec，runtime 取路由声明的 runtime。
+    pub fn new(route: &'a RouteMeta, params: CanonicalParams) -> Self {
+        Self {
+            route,
+            target_stack: route.preferred_stack.clone(),
+            transition: route.transition_spec.clone(),
+            runtime: route.runtime,
+            params,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// 以解析到的路由与导航请求创建上下文（例如处理重定向请求时）：
+    /// 请求中指定的目标栈、动画、展示模式与扩展数据覆盖路由默认配置。
+    pub fn from_request(route: &'a RouteMeta, request: NavRequest) -> Self {
+        let mut ctx = Self::new(route, request.params);
+        ctx.extensions = request.extensions;
+        ctx.apply_rewrite(NavRewrite {
+            target_stack: request.target_stack,
+            presentation: request.presentation,
+            transition: request.transition,
+        });
+        ctx
+    }
+
+    /// 应用中间件返回的改写。
+    pub fn apply_rewrite(&mut self, rewrite: NavRewrite) {
+        if let Some(stack) = rewrite.target_stack {
+            self.target_stack = stack;
+        }
+        if let Some(transition) = rewrite.transition {
+            self.transition = transition;
+        }
+        if let Some(presentation) = rewrite.presentation {
+            self.transition.presentation = presentation;
+        }
+    }
+
+    /// 构造 matcher 使用的匹配上下文。
+    pub fn match_context(&self) -> MatchContext<'_> {
+        MatchContext {
+            route: self.route,
+            target_stack: &self.target_stack,
+            runtime: self.runtime,
+            params: &self.params,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::umrouter_core::middleware::Extensions;
+use crate::umrouter_core::route::TransitionSpec;
+use crate::umrouter_core::types::{CanonicalParams, PresentationMode, StackId};
+
+/// 导航目标：按路由名或按路径定位。
+///
+/// - Name：例如 "auth.login"，通过 RouteStore 的 name 索引解析
+/// - Path：例如 "/orders/123/detail"，通过 path 匹配解析
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavTarget {
+    /// 按路由名定位。
+    Name(String),
+
+    /// 按路径定位。
+    Path(String),
+}
+
+/// 一次完整的导航请求（RFC Phase 1 - Request Intake）。
+///
+/// 既可以作为外部发起的导航输入，
+/// 也可以作为中间件重定向时携带的新请求。
+#[derive(Debug, Clone)]
+pub struct NavRequest {
+    /// 导航目标（name / path）。
+    pub target: NavTarget,
+
+    /// 请求参数。
+    pub params: CanonicalParams,
+
+    /// 指定的目标栈（为空时使用路由的 preferred_stack）。
+    pub target_stack: Option<StackId>,
+
+    /// 指定的展示模式（为空时使用路由的 TransitionSpec）。
+    pub presentation: Option<PresentationMode>,
+
+    /// 指定的动画与展示配置（为空时使用路由的 TransitionSpec）；
+    /// 与 `presentation` 同时设置时，`presentation` 覆盖其中的展示模式。
+    pub transition: Option<TransitionSpec>,
+
+    /// 带入新导航的扩展数据（例如重定向前已完成的实验分配）。
+    pub extensions: Extensions,
+}
+
+impl NavRequest {
+    /// 按路由名创建请求。
+    pub fn by_name(name: impl Into<String>) -> Self {
+        Self::new(NavTarget::Name(name.into()))
+    }
+
+    /// 按路径创建请求。
+    pub fn by_path(path: impl Into<String>) -> Self {
+        Self::new(NavTarget::Path(path.into()))
+    }
+
+    /// 以指定目标创建请求，其余字段为默认值。
+    pub fn new(target: NavTarget) -> Self {
+        Self {
+            target,
+            params: CanonicalParams::default(),
+            target_stack: None,
+            presentation: None,
+            transition: None,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// 设置请求参数。
+    pub fn with_params(mut self, params: CanonicalParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// 设置目标栈。
+    pub fn with_stack(mut self, stack: StackId) -> Self {
+        self.target_stack = Some(stack);
+        self
+    }
+
+    /// 设置展示模式。
+    pub fn with_presentation(mut self, presentation: PresentationMode) -> Self {
+        self.presentation = Some(presentation);
+        self
+    }
+
+    /// 设置动画与展示配置。
+    pub fn with_transition(mut self, transition: TransitionSpec) -> Self {
+        self.transition = Some(transition);
+        self
+    }
+
+    /// 设置带入新导航的扩展数据。
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+}
@@ -0,0 +1,1054 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use super::context::NavigationContext;
+use super::request::NavRequest;
+use crate::umrouter_core::middleware::{
+    BoxFuture, BudgetPolicy, ExecuteContext, Extensions, FailureStage, Middleware,
+    MiddlewareExecutor, MiddlewareFailure, MiddlewareRegistry, MiddlewareResult, failure,
+};
+use crate::umrouter_core::types::{CanonicalParams, MiddlewareId, RuntimeKind};
+
+//
+// ========== 取消 ==========
+//
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// 导航取消令牌。
+///
+/// 可以在任意线程调用 `cancel()`；
+/// 正在等待异步 executor 的 runner 会被唤醒并立即结束本次导航。
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    state: Arc<CancelState>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消整个导航。
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.lock_wakers());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// 是否已取消。
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.lock_wakers();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn lock_wakers(&self) -> std::sync::MutexGuard<'_, Vec<Waker>> {
+        self.state.wakers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 截止时间的来源。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeadlineSource {
+    /// 中间件自身的预算。
+    Own,
+
+    /// 整段链的全局预算。
+    Global,
+}
+
+/// 异步 executor 单次等待的结果。
+enum Wait<T> {
+    Ready(T),
+    Cancelled,
+    Deadline(DeadlineSource),
+    Panicked(Box<dyn Any + Send>),
+}
+
+/// 在等待 executor 的同时监听取消令牌与截止时间。
+///
+/// 每次被 poll 时都会检查截止时间；提供了定时器时，
+/// 到期的 sleep future 会唤醒 runner，即使 executor 自身不再被唤醒。
+/// 取消或超过截止时间时丢弃内部 future。
+struct Guarded<'a, T> {
+    token: &'a CancelToken,
+    deadline: Option<(Instant, DeadlineSource)>,
+    sleep: Option<BoxFuture<'static, ()>>,
+    future: BoxFuture<'a, T>,
+}
+
+impl<T> Future for Guarded<'_, T> {
+    type Output = Wait<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(Wait::Cancelled);
+        }
+        if let Some((deadline, source)) = self.deadline
+            && (Instant::now() >= deadline
+                || self
+                    .sleep
+                    .as_mut()
+                    .is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready()))
+        {
+            return Poll::Ready(Wait::Deadline(source));
+        }
+        self.token.register(cx.waker());
+        // 注册后再检查一次，避免与 cancel() 竞争时丢失唤醒。
+        if self.token.is_cancelled() {
+            return Poll::Ready(Wait::Cancelled);
+        }
+        let future = &mut self.future;
+        match catch_panic(|| future.as_mut().poll(cx)) {
+            Ok(poll) => poll.map(Wait::Ready),
+            Err(payload) => Poll::Ready(Wait::Panicked(payload)),
+        }
+    }
+}
+
+/// 由宿主 async runtime 提供的定时器。
+///
+/// runner 本身不依赖任何 runtime；没有定时器时，截止时间只能在 executor
+/// 唤醒 runner 时检查，一直挂起的 executor 会拖住整条链。
+pub trait Timer: Send + Sync {
+    /// 返回一个在 `duration` 后完成的 future（例如 `tokio::time::sleep`）。
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+//
+// ========== 执行结果 ==========
+//
+
+/// 一段中间件链的执行结果。
+#[derive(Debug, Clone)]
+pub enum PipelineResult {
+    /// 所有中间件都已执行（或被跳过），导航继续。
+    Completed,
+
+    /// 某个中间件中止了导航。
+    Aborted { by: MiddlewareId, reason: String },
+
+    /// 某个中间件要求重定向，pipeline 需要以新请求重新开始。
+    Redirected {
+        by: MiddlewareId,
+        request: NavRequest,
+        continuation: Option<Box<NavRequest>>,
+    },
+
+    /// 导航被取消。
+    ///
+    /// `during` 为取消时正在等待的中间件（如果有）。
+    Cancelled { during: Option<MiddlewareId> },
+
+    /// 超出时间预算且策略为 Abort。
+    ///
+    /// `by` 为超时的中间件；整条链超出全局预算时为 None。
+    OverBudget { by: Option<MiddlewareId> },
+
+    /// 策略为 FailClosed 的中间件 panic，导航被中止。
+    Failed(MiddlewareFailure),
+}
+
+/// 单个中间件的执行耗时记录。
+#[derive(Debug, Clone)]
+pub struct MiddlewareTiming {
+    /// 中间件 id。
+    pub id: MiddlewareId,
+
+    /// 执行耗时。
+    pub elapsed: Duration,
+
+    /// 该中间件声明的时间预算。
+    pub budget: Option<Duration>,
+
+    /// 是否超出预算（中间件自身预算）。
+    pub over_budget: bool,
+
+    /// 结果是否因超时被丢弃（BudgetPolicy::Skip）。
+    pub skipped: bool,
+}
+
+/// 一次导航中累计的中间件耗时。
+///
+/// runner 每执行完一段链都会把耗时追加到 Extensions 中，
+/// PostRO 的埋点中间件可以通过 `ctx.extensions.get::<PipelineTimings>()` 上报慢中间件。
+#[derive(Debug, Clone, Default)]
+pub struct PipelineTimings(pub Vec<MiddlewareTiming>);
+
+impl PipelineTimings {
+    /// 超出预算的中间件记录。
+    pub fn offenders(&self) -> impl Iterator<Item = &MiddlewareTiming> {
+        self.0.iter().filter(|t| t.over_budget)
+    }
+}
+
+/// 链执行结束时按结果调用的回调。
+///
+/// 中间件把它放进 Extensions 来登记"导航结束后"要做的事（例如提交或撤销预留），
+/// runner 在 `run` 返回前取出并依次调用，因此每个回调只会被调用一次；
+/// 导航在中途被丢弃时回调不会被调用，需要兜底的一方应自行实现 Drop。
+#[derive(Clone, Default)]
+pub struct OutcomeHooks(Vec<Arc<OutcomeHook>>);
+
+type OutcomeHook = dyn Fn(&PipelineResult) + Send + Sync;
+
+impl OutcomeHooks {
+    /// 追加一个回调。
+    pub fn push(&mut self, hook: impl Fn(&PipelineResult) + Send + Sync + 'static) {
+        self.0.push(Arc::new(hook));
+    }
+}
+
+impl std::fmt::Debug for OutcomeHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("OutcomeHooks").field(&self.0.len()).finish()
+    }
+}
+
+/// 中间件链执行后的完整输出。
+#[derive(Debug, Clone)]
+pub struct PipelineOutcome {
+    /// 执行结果。
+    pub result: PipelineResult,
+
+    /// 每个已执行中间件的耗时（按执行顺序）。
+    pub timings: Vec<MiddlewareTiming>,
+
+    /// 整段链的总耗时。
+    pub elapsed: Duration,
+
+    /// 是否超出全局预算。
+    pub over_budget: bool,
+
+    /// 执行期间 panic 的中间件（包括被 fail-open 跳过的）。
+    pub failures: Vec<MiddlewareFailure>,
+}
+
+impl PipelineOutcome {
+    /// 导航是否可以继续。
+    pub fn is_completed(&self) -> bool {
+        matches!(self.result, PipelineResult::Completed)
+    }
+
+    /// 实际执行过的中间件（按执行顺序）。
+    pub fn executed(&self) -> impl Iterator<Item = &MiddlewareId> {
+        self.timings.iter().map(|t| &t.id)
+    }
+}
+
+//
+// ========== Runner ==========
+//
+
+/// 单个中间件执行后的中间结果。
+struct Executed {
+    wait: Wait<MiddlewareResult>,
+
+    /// 在快照上执行时的参数与扩展数据，由调用方决定是否写回。
+    staged: Option<(CanonicalParams, Extensions)>,
+
+    /// 执行后的 runtime（ReadWrite 中间件可以改写）。
+    runtime: RuntimeKind,
+}
+
+/// 中间件链执行器。
+///
+/// - 按链的顺序（即 priority 顺序）依次执行，同步与异步 executor 可以混合
+/// - 不依赖任何 async runtime，`run` 返回的 future 可以由任意 executor 驱动
+/// - ReadWrite 中间件可以修改参数、扩展数据与 runtime，
+///   返回的 Rewrite 会立即应用到导航上下文
+/// - ReadOnly 中间件与 PostRO 阶段的中间件拿到的是参数与扩展数据的快照，
+///   修改不会写回，返回的 Rewrite / Abort / Redirect 也会被忽略
+/// - 中间件预算与全局预算按各自的 BudgetPolicy 处理；
+///   同步 executor 无法被打断，只能在返回后判定超时；
+///   异步 executor 需要通过 `with_timer` 提供定时器才能准时被打断
+/// - executor panic 会被捕获并按中间件的 FailurePolicy 处理，同时计入注册表的熔断计数；
+///   直接在导航上下文上执行的 ReadWrite 中间件，panic 前已做的修改不会回滚
+/// - 结束时调用 Extensions 中登记的 `OutcomeHooks`
+pub struct PipelineRunner<'r> {
+    registry: &'r MiddlewareRegistry,
+    cancel: CancelToken,
+    budget: Option<(Duration, BudgetPolicy)>,
+    timer: Option<Arc<dyn Timer>>,
+}
+
+impl<'r> PipelineRunner<'r> {
+    pub fn new(registry: &'r MiddlewareRegistry) -> Self {
+        Self {
+            registry,
+            cancel: CancelToken::new(),
+            budget: None,
+            timer: None,
+        }
+    }
+
+    /// 使用外部提供的取消令牌。
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// 设置整段链的全局时间预算。
+    ///
+    /// 超出后：Continue 只做记录；Abort 中止导航；Skip 跳过剩余中间件并视为完成。
+    /// 链中有鉴权等守卫时应使用 Abort，Skip 会让守卫不再执行。
+    pub fn with_budget(mut self, budget: Duration, policy: BudgetPolicy) -> Self {
+        self.budget = Some((budget, policy));
+        self
+    }
+
+    /// 使用宿主提供的定时器，让异步 executor 在截止时间准时被打断。
+    pub fn with_timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.timer = Some(timer);
+        self
+    }
+
+    /// 当前使用的取消令牌。
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    /// 执行一段中间件链。
+    ///
+    /// 链中已不在注册表里、已停用或已熔断的 id 会被跳过。
+    pub async fn run(
+        &self,
+        chain: &[MiddlewareId],
+        nav: &mut NavigationContext<'_>,
+    ) -> PipelineOutcome {
+        let started = Instant::now();
+        let mut timings = Vec::new();
+        let mut over_budget = false;
+        let mut failures = Vec::new();
+
+        let result = 'chain: {
+            for id in chain {
+                if self.cancel.is_cancelled() {
+                    break 'chain PipelineResult::Cancelled { during: None };
+                }
+                let Some(middleware) = self.registry.get(id) else {
+                    continue;
+                };
+                if !self.registry.is_active(id) {
+                    continue;
+                }
+
+                if let Some((budget, policy)) = self.budget
+                    && started.elapsed() > budget
+                {
+                    over_budget = true;
+                    match policy {
+                        BudgetPolicy::Continue => {}
+                        BudgetPolicy::Abort => {
+                            break 'chain PipelineResult::OverBudget { by: None };
+                        }
+                        BudgetPolicy::Skip => break 'chain PipelineResult::Completed,
+                    }
+                }
+
+                let step_started = Instant::now();
+                let deadline = self.deadline(middleware, started, step_started);
+                let executed = self.execute(middleware, nav, deadline).await;
+                let elapsed = step_started.elapsed();
+
+                let mw_over_budget = match executed.wait {
+                    Wait::Deadline(_) => middleware.budget.is_some_and(|b| elapsed >= b),
+                    _ => middleware.budget.is_some_and(|b| elapsed > b),
+                };
+                let skipped = mw_over_budget && middleware.budget_policy == BudgetPolicy::Skip;
+                timings.push(MiddlewareTiming {
+                    id: id.clone(),
+                    elapsed,
+                    budget: middleware.budget,
+                    over_budget: mw_over_budget,
+                    skipped,
+                });
+
+                let result = match executed.wait {
+                    Wait::Cancelled => {
+                        break 'chain PipelineResult::Cancelled {
+                            during: Some(id.clone()),
+                        };
+                    }
+                    Wait::Panicked(payload) => {
+                        let failure = failure(middleware, FailureStage::Executor, payload);
+                        self.registry.record_failure(&failure);
+                        failures.push(failure.clone());
+                        if failure.is_blocking() {
+                            break 'chain PipelineResult::Failed(failure);
+                        }
+                        continue;
+                    }
+                    Wait::Deadline(DeadlineSource::Global) => {
+                        // 全局预算到期时按全局策略处理，与中间件自身预算是否超出无关。
+                        over_budget = true;
+                        match self.budget.map(|(_, policy)| policy) {
+                            Some(BudgetPolicy::Skip) => break 'chain PipelineResult::Completed,
+                            _ => break 'chain PipelineResult::OverBudget { by: None },
+                        }
+                    }
+                    _ if mw_over_budget && middleware.budget_policy == BudgetPolicy::Abort => {
+                        break 'chain PipelineResult::OverBudget {
+                            by: Some(id.clone()),
+                        };
+                    }
+                    _ if skipped => continue,
+                    Wait::Deadline(DeadlineSource::Own) => continue,
+                    Wait::Ready(result) => result,
+                };
+
+                if middleware.is_read_only() {
+                    continue;
+                }
+                if let Some((params, extensions)) = executed.staged {
+                    nav.params = params;
+                    nav.extensions = extensions;
+                }
+                nav.runtime = executed.runtime;
+                match result {
+                    MiddlewareResult::Continue => {}
+                    MiddlewareResult::Rewrite(rewrite) => nav.apply_rewrite(rewrite),
+                    MiddlewareResult::Abort { reason } => {
+                        break 'chain PipelineResult::Aborted {
+                            by: id.clone(),
+                            reason,
+                        };
+                    }
+                    MiddlewareResult::Redirect {
+                        request,
+                        continuation,
+                    } => {
+                        break 'chain PipelineResult::Redirected {
+                            by: id.clone(),
+                            request,
+                            continuation,
+                        };
+                    }
+                }
+            }
+            PipelineResult::Completed
+        };
+
+        let mut recorded = nav
+            .extensions
+            .get::<PipelineTimings>()
+            .cloned()
+            .unwrap_or_default();
+        recorded.0.extend(timings.iter().cloned());
+        nav.extensions.insert(recorded);
+        if let Some(hooks) = nav.extensions.remove::<OutcomeHooks>() {
+            for hook in &hooks.0 {
+                hook(&result);
+            }
+        }
+
+        PipelineOutcome {
+            result,
+            timings,
+            elapsed: started.elapsed(),
+            over_budget,
+            failures,
+        }
+    }
+
+    /// 计算异步 executor 的截止时间及其来源：
+    /// 只有策略不是 Continue 的预算才需要提前打断，两者同时到期时按全局预算处理。
+    fn deadline(
+        &self,
+        middleware: &Middleware,
+        chain_started: Instant,
+        step_started: Instant,
+    ) -> Option<(Instant, DeadlineSource)> {
+        let own = middleware
+            .budget
+            .filter(|_| middleware.budget_policy != BudgetPolicy::Continue)
+            .map(|b| (step_started + b, DeadlineSource::Own));
+        let global = self
+            .budget
+            .filter(|(_, policy)| *policy != BudgetPolicy::Continue)
+            .map(|(b, _)| (chain_started + b, DeadlineSource::Global));
+        match (own, global) {
+            (Some(own), Some(global)) if own.0 < global.0 => Some(own),
+            (own, global) => global.or(own),
+        }
+    }
+
+    /// 执行单个中间件。
+    async fn execute(
+        &self,
+        middleware: &Middleware,
+        nav: &mut NavigationContext<'_>,
+        deadline: Option<(Instant, DeadlineSource)>,
+    ) -> Executed {
+        // 只读中间件、以及超时需要丢弃修改的中间件，都在快照上执行。
+        let isolate = middleware.is_read_only()
+            || (middleware.budget.is_some() && middleware.budget_policy == BudgetPolicy::Skip);
+        let mut staged = isolate.then(|| (nav.params.clone(), nav.extensions.clone()));
+        let (params, extensions) = match &mut staged {
+            Some((params, extensions)) => (params, extensions),
+            None => (&mut nav.params, &mut nav.extensions),
+        };
+        let mut ctx = ExecuteContext {
+            route: nav.route,
+            target_stack: &nav.target_stack,
+            transition: &nav.transition,
+            runtime: nav.runtime,
+            params,
+            extensions,
+        };
+
+        let wait = match &middleware.executor {
+            MiddlewareExecutor::Sync(executor) => {
+                match catch_panic(|| executor.execute(&mut ctx)) {
+                    Ok(result) => Wait::Ready(result),
+                    Err(payload) => Wait::Panicked(payload),
+                }
+            }
+            MiddlewareExecutor::Async(executor) => {
+                let ctx = &mut ctx;
+                match catch_panic(move || executor.execute(ctx)) {
+                    Ok(future) => {
+                        let sleep =
+                            self.timer
+                                .as_ref()
+                                .zip(deadline)
+                                .map(|(timer, (deadline, _))| {
+                                    timer.sleep(deadline.saturating_duration_since(Instant::now()))
+                                });
+                        Guarded {
+                            token: &self.cancel,
+                            deadline,
+                            sleep,
+                            future,
+                        }
+                        .await
+                    }
+                    Err(payload) => Wait::Panicked(payload),
+                }
+            }
+        };
+        let runtime = ctx.runtime;
+        Executed {
+            wait,
+            staged,
+            runtime,
+        }
+    }
+}
+
+/// 执行闭包并捕获 panic。
+fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, Box<dyn Any + Send>> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+}
+
+impl std::fmt::Debug for PipelineRunner<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipelineRunner")
+            .field("registry", &self.registry)
+            .field("cancelled", &self.cancel.is_cancelled())
+            .field("budget", &self.budget)
+            .field("timer", &self.timer.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::middleware::NavRewrite;
+    use crate::umrouter_core::middleware::{
+        AccessMode, AlwaysMatcher, AsyncExecutor, FailurePolicy, FnExecutor, FnMatcher, FnObserver,
+        MatchContext, MiddlewarePhase,
+    };
+    use crate::umrouter_core::test_support::{block_on, middleware, route};
+    use crate::umrouter_core::types::{CanonicalParams, PresentationMode, StackId};
+
+    /// 第一次 poll 返回 Pending，之后追加标记并返回 Continue。
+    struct YieldOnce(&'static str);
+
+    impl AsyncExecutor for YieldOnce {
+        fn execute<'a, 'c: 'a>(
+            &'a self,
+            ctx: &'a mut ExecuteContext<'c>,
+        ) -> BoxFuture<'a, MiddlewareResult> {
+            Box::pin(async move {
+                let mut yielded = false;
+                std::future::poll_fn(|cx| {
+                    if yielded {
+                        Poll::Ready(())
+                    } else {
+                        yielded = true;
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                })
+                .await;
+                append(ctx, self.0);
+                MiddlewareResult::Continue
+            })
+        }
+    }
+
+    /// 取消令牌后永远挂起。
+    struct CancelAndHang(CancelToken);
+
+    impl AsyncExecutor for CancelAndHang {
+        fn execute<'a, 'c: 'a>(
+            &'a self,
+            _ctx: &'a mut ExecuteContext<'c>,
+        ) -> BoxFuture<'a, MiddlewareResult> {
+            self.0.cancel();
+            Box::pin(std::future::pending())
+        }
+    }
+
+    /// 永远挂起且从不唤醒 runner。
+    struct Hang;
+
+    impl AsyncExecutor for Hang {
+        fn execute<'a, 'c: 'a>(
+            &'a self,
+            _ctx: &'a mut ExecuteContext<'c>,
+        ) -> BoxFuture<'a, MiddlewareResult> {
+            Box::pin(std::future::pending())
+        }
+    }
+
+    /// 用线程实现的定时器。
+    struct ThreadTimer;
+
+    impl Timer for ThreadTimer {
+        fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+            let done = Arc::new(AtomicBool::new(false));
+            let mut started = false;
+            Box::pin(std::future::poll_fn(move |cx| {
+                if done.load(Ordering::SeqCst) {
+                    return Poll::Ready(());
+                }
+                if !started {
+                    started = true;
+                    let (done, waker) = (done.clone(), cx.waker().clone());
+                    std::thread::spawn(move || {
+                        std::thread::sleep(duration);
+                        done.store(true, Ordering::SeqCst);
+                        waker.wake();
+                    });
+                }
+                Poll::Pending
+            }))
+        }
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    fn append(ctx: &mut ExecuteContext, mark: &str) {
+        let order = ctx.extensions.get_str("order").unwrap_or_default();
+        let order = format!("{order}{mark}");
+        ctx.extensions.insert_str("order", order);
+    }
+
+    fn marker(mark: &'static str) -> FnExecutor<impl Fn(&mut ExecuteContext) -> MiddlewareResult> {
+        FnExecutor::new(mark, move |ctx: &mut ExecuteContext| {
+            append(ctx, mark);
+            MiddlewareResult::Continue
+        })
+    }
+
+    #[test]
+    fn mixes_sync_and_async_in_priority_order() {
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(middleware(
+                "a",
+                MiddlewarePhase::PreRW,
+                0,
+                AlwaysMatcher,
+                marker("a"),
+            ))
+            .unwrap();
+        let mut async_mw = middleware("b", MiddlewarePhase::PreRW, 1, AlwaysMatcher, marker("_"));
+        async_mw.executor = MiddlewareExecutor::asynchronous(YieldOnce("b"));
+        registry.register(async_mw).unwrap();
+        registry
+            .register(middleware(
+                "c",
+                MiddlewarePhase::PreRW,
+                2,
+                AlwaysMatcher,
+                marker("c"),
+            ))
+            .unwrap();
+
+        let meta = route("home.index", "/home", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+
+        let runner = PipelineRunner::new(&registry);
+        let future = runner.run(&chain, &mut nav);
+        assert_send(&future);
+        let outcome = block_on(future);
+        assert!(outcome.is_completed());
+        assert_eq!(nav.extensions.get_str("order"), Some("abc"));
+    }
+
+    #[test]
+    fn cancels_while_awaiting() {
+        let token = CancelToken::new();
+        let mut registry = MiddlewareRegistry::new();
+        let mut hang = middleware(
+            "hang",
+            MiddlewarePhase::PreRW,
+            0,
+            AlwaysMatcher,
+            marker("_"),
+        );
+        hang.executor = MiddlewareExecutor::asynchronous(CancelAndHang(token.clone()));
+        registry.register(hang).unwrap();
+        registry
+            .register(middleware(
+                "after",
+                MiddlewarePhase::PreRW,
+                1,
+                AlwaysMatcher,
+                marker("x"),
+            ))
+            .unwrap();
+
+        let meta = route("home.index", "/home", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+
+        let runner = PipelineRunner::new(&registry).with_cancel_token(token);
+        let outcome = block_on(runner.run(&chain, &mut nav));
+        assert!(matches!(
+            outcome.result,
+            PipelineResult::Cancelled { during: Some(MiddlewareId(ref id)) } if id == "hang"
+        ));
+        assert_eq!(nav.extensions.get_str("order"), None);
+    }
+
+    fn slow_writer() -> FnExecutor<impl Fn(&mut ExecuteContext) -> MiddlewareResult> {
+        FnExecutor::new("slow", |ctx: &mut ExecuteContext| {
+            std::thread::sleep(Duration::from_millis(20));
+            ctx.params.map.insert("slow".into(), true.into());
+            MiddlewareResult::Continue
+        })
+    }
+
+    #[test]
+    fn applies_budget_policies_and_records_timings() {
+        let meta = route("home.index", "/home", &[]);
+        let budget = Duration::from_millis(1);
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(
+                middleware(
+                    "slow",
+                    MiddlewarePhase::PreRW,
+                    0,
+                    AlwaysMatcher,
+                    slow_writer(),
+                )
+                .with_budget(budget, BudgetPolicy::Skip),
+            )
+            .unwrap();
+        registry
+            .register(middleware(
+                "after",
+                MiddlewarePhase::PreRW,
+                1,
+                AlwaysMatcher,
+                marker("x"),
+            ))
+            .unwrap();
+
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert!(outcome.timings[0].over_budget && outcome.timings[0].skipped);
+        assert!(!nav.params.map.contains_key("slow"));
+        assert_eq!(nav.extensions.get_str("order"), Some("x"));
+
+        let timings = nav.extensions.get::<PipelineTimings>().unwrap();
+        let offenders: Vec<_> = timings.offenders().map(|t| t.id.0.as_str()).collect();
+        assert_eq!(offenders, ["slow"]);
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(
+                middleware(
+                    "slow",
+                    MiddlewarePhase::PreRW,
+                    0,
+                    AlwaysMatcher,
+                    slow_writer(),
+                )
+                .with_budget(budget, BudgetPolicy::Abort),
+            )
+            .unwrap();
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain[..1], &mut nav));
+        assert!(matches!(
+            outcome.result,
+            PipelineResult::OverBudget { by: Some(MiddlewareId(ref id)) } if id == "slow"
+        ));
+    }
+
+    #[test]
+    fn isolates_panics_and_trips_after_threshold() {
+        let meta = route("home.index", "/home", &[]);
+        let boom = || {
+            FnExecutor::new("boom", |_: &mut ExecuteContext| -> MiddlewareResult {
+                panic!("boom")
+            })
+        };
+
+        let mut registry = MiddlewareRegistry::new();
+        registry.set_panic_threshold(Some(2));
+        registry
+            .register(middleware(
+                "bad_matcher",
+                MiddlewarePhase::PreRW,
+                0,
+                FnMatcher::new("bad", |_: &MatchContext| panic!("matcher")),
+                marker("m"),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "open",
+                MiddlewarePhase::PreRW,
+                1,
+                AlwaysMatcher,
+                boom(),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "after",
+                MiddlewarePhase::PreRW,
+                2,
+                AlwaysMatcher,
+                marker("x"),
+            ))
+            .unwrap();
+
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let resolved = registry.resolve_chain(&nav.match_context());
+        assert_eq!(resolved.failures.len(), 1);
+        assert_eq!(resolved.failures[0].stage, FailureStage::Matcher);
+        assert!(resolved.blocking_failure().is_none());
+
+        let outcome =
+            block_on(PipelineRunner::new(&registry).run(&resolved.pre_rw_chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(outcome.failures[0].message, "boom");
+        assert_eq!(nav.extensions.get_str("order"), Some("x"));
+
+        // 第二次 panic 后两个中间件都被熔断，不再出现在链中。
+        let resolved = registry.resolve_chain(&nav.match_context());
+        block_on(PipelineRunner::new(&registry).run(&resolved.pre_rw_chain, &mut nav));
+        let resolved = registry.resolve_chain(&nav.match_context());
+        assert_eq!(resolved.pre_rw_chain, vec![MiddlewareId("after".into())]);
+        assert!(resolved.failures.is_empty());
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(
+                middleware("closed", MiddlewarePhase::PreRW, 0, AlwaysMatcher, boom())
+                    .with_failure_policy(FailurePolicy::FailClosed),
+            )
+            .unwrap();
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = [MiddlewareId("closed".into())];
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(matches!(
+            outcome.result,
+            PipelineResult::Failed(ref f) if f.id.0 == "closed" && f.stage == FailureStage::Executor
+        ));
+    }
+
+    #[test]
+    fn applies_rewrites_without_restarting() {
+        let rewrite = |id, access_mode| {
+            middleware(
+                id,
+                MiddlewarePhase::PreRW,
+                0,
+                AlwaysMatcher,
+                FnExecutor::new(id, |_: &mut ExecuteContext| {
+                    MiddlewareResult::rewrite(
+                        NavRewrite::new()
+                            .with_stack(StackId("tablet".into()))
+                            .with_presentation(PresentationMode::Sheet),
+                    )
+                }),
+            )
+            .with_access_mode(access_mode)
+        };
+        let observe = FnExecutor::new("observe", |ctx: &mut ExecuteContext| {
+            let seen = format!("{}:{:?}", ctx.target_stack.0, ctx.transition.presentation);
+            ctx.extensions.insert_str("seen", seen);
+            MiddlewareResult::Continue
+        });
+
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(rewrite("ro", AccessMode::ReadOnly))
+            .unwrap();
+        registry
+            .register(middleware(
+                "observe",
+                MiddlewarePhase::PreRW,
+                2,
+                AlwaysMatcher,
+                observe,
+            ))
+            .unwrap();
+
+        let meta = route("home.index", "/home", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        // 只读中间件的改写被忽略。
+        assert_eq!(nav.extensions.get_str("seen"), Some("main:Push"));
+
+        registry
+            .register(rewrite("rw", AccessMode::ReadWrite))
+            .unwrap();
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(outcome.executed().count(), 3);
+        assert_eq!(nav.extensions.get_str("seen"), Some("tablet:Sheet"));
+        assert_eq!(nav.target_stack, StackId("tablet".into()));
+        assert_eq!(nav.transition.presentation, PresentationMode::Sheet);
+    }
+
+    #[test]
+    fn post_ro_middlewares_run_read_only() {
+        // 未声明 ReadOnly 的 PostRO 中间件同样不能修改上下文或改变导航结果。
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register(middleware(
+                "post",
+                MiddlewarePhase::PostRO,
+                0,
+                AlwaysMatcher,
+                FnExecutor::new("post", |ctx: &mut ExecuteContext| {
+                    ctx.params.map.insert("post".into(), true.into());
+                    append(ctx, "p");
+                    MiddlewareResult::Redirect {
+                        request: NavRequest::by_name("home.index"),
+                        continuation: None,
+                    }
+                }),
+            ))
+            .unwrap();
+        registry
+            .register(middleware(
+                "abort",
+                MiddlewarePhase::PostRO,
+                1,
+                AlwaysMatcher,
+                FnExecutor::new("abort", |_: &mut ExecuteContext| MiddlewareResult::Abort {
+                    reason: "late".into(),
+                }),
+            ))
+            .unwrap();
+        let seen = Arc::new(Mutex::new(None));
+        let observed = seen.clone();
+        registry
+            .register(middleware(
+                "observe",
+                MiddlewarePhase::PostRO,
+                2,
+                AlwaysMatcher,
+                FnObserver::new("observe", move |ctx| {
+                    *observed.lock().unwrap() = ctx.extensions.get_str("user").map(str::to_owned);
+                }),
+            ))
+            .unwrap();
+
+        let meta = route("home.index", "/home", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        nav.extensions.insert_str("user", "7");
+        let chain = registry.resolve_chain(&nav.match_context()).post_ro_chain;
+        let outcome = block_on(PipelineRunner::new(&registry).run(&chain, &mut nav));
+        assert!(outcome.is_completed());
+        assert_eq!(outcome.executed().count(), 3);
+        assert!(!nav.params.map.contains_key("post"));
+        assert_eq!(nav.extensions.get_str("order"), None);
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("7"));
+        assert_eq!(nav.extensions.get_str("user"), Some("7"));
+    }
+
+    #[test]
+    fn timer_interrupts_hanging_executor() {
+        let mut hang = middleware(
+            "hang",
+            MiddlewarePhase::PreRW,
+            0,
+            AlwaysMatcher,
+            marker("_"),
+        )
+        .with_budget(Duration::from_millis(5), BudgetPolicy::Abort);
+        hang.executor = MiddlewareExecutor::asynchronous(Hang);
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(hang).unwrap();
+
+        let meta = route("home.index", "/home", &[]);
+        let mut nav = NavigationContext::new(&meta, CanonicalParams::default());
+        let chain = [MiddlewareId("hang".into())];
+        let runner = PipelineRunner::new(&registry).with_timer(Arc::new(ThreadTimer));
+        let outcome = block_on(runner.run(&chain, &mut nav));
+        assert!(matches!(
+            outcome.result,
+            PipelineResult::OverBudget { by: Some(MiddlewareId(ref id)) } if id == "hang"
+        ));
+        assert!(outcome.timings[0].over_budget);
+
+        // 自身预算为 Continue 的中间件已超出自身预算时，全局 Abort 预算到期仍然中止导航。
+        let mut hang = middleware(
+            "hang",
+            MiddlewarePhase::PreRW,
+            0,
+            AlwaysMatcher,
+            marker("_"),
+        )
+        .with_budget(Duration::from_millis(1), BudgetPolicy::Continue);
+        hang.executor = MiddlewareExecutor::asynchronous(Hang);
+        let mut registry = MiddlewareRegistry::new();
+        registry.register(hang).unwrap();
+        let runner = PipelineRunner::new(&registry)
+            .with_budget(Duration::from_millis(10), BudgetPolicy::Abort)
+            .with_timer(Arc::new(ThreadTimer));
+        let outcome = block_on(runner.run(&chain, &mut nav));
+        assert!(matches!(
+            outcome.result,
+            PipelineResult::OverBudget { by: None }
+        ));
+        assert!(outcome.over_budget && outcome.timings[0].over_budget);
+    }
+}
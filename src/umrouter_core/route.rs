@@ -5,4 +5,3 @@ mod store;
 pub use meta::*;
 pub use resolved::*;
 pub use store::*;
-
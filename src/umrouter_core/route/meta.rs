@@ -6,7 +6,9 @@ use crate::umrouter_core::types::{
 ///
 /// 你可以决定是：
 /// - "一份 schema 内部分 path/query/body 三块"
+///
 /// 或
+///
 /// - "三份 schema 分开存"
 ///
 /// 这里仅作为占位，具体结构可后续细化。
@@ -32,7 +34,7 @@ pub struct HookSpec {
 }
 
 /// 动画/展示偏好。
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TransitionSpec {
     /// 页面展示模式：push / modal / sheet 等。
     pub presentation: PresentationMode,
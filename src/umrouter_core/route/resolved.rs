@@ -20,4 +20,3 @@ pub struct ResolvedRoute<'a> {
     /// path 参数（":id" / ":orderId" 等）。
     pub path_params: BTreeMap<String, String>,
 }
-
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
 use matchit::Router as MatchitRouter;
 
 use super::meta::RouteMeta;
+use super::resolved::ResolvedRoute;
 use crate::umrouter_core::types::RouteId;
 
 /// 路由表存储结构：
@@ -13,16 +15,147 @@ use crate::umrouter_core::types::RouteId;
 #[derive(Debug)]
 pub struct RouteStore {
     /// 所有路由的元信息，索引下标就是内部 RouteId 的值。
-    pub metas: Vec<RouteMeta>,
+    metas: Vec<RouteMeta>,
 
     /// name-based 索引："auth.profile" -> RouteId
-    pub name_index: HashMap<String, RouteId>,
+    name_index: HashMap<String, RouteId>,
 
     /// path-based 索引，使用 matchit 做底层结构。
     ///
     /// 例如：
     ///     "/auth/profile" -> RouteId(1)
     ///     "/orders/:orderId/detail" -> RouteId(2)
-    pub path_router: MatchitRouter<RouteId>,
+    path_router: MatchitRouter<RouteId>,
+
+    /// 路由表版本号，每次变更递增。
+    ///
+    /// 依赖路由元信息的缓存（例如中间件链缓存）据此判断是否失效。
+    version: u64,
+}
+
+/// 路由注册错误。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteStoreError {
+    /// 路由名已存在。
+    DuplicateName(String),
+
+    /// 路径模式无法插入路由树（格式错误或与已有路径冲突）。
+    InvalidPath { path: String, message: String },
+}
+
+impl fmt::Display for RouteStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "route name `{name}` is already registered"),
+            Self::InvalidPath { path, message } => {
+                write!(f, "invalid route path `{path}`: {message}")
+            }
+        }
+    }
 }
 
+impl std::error::Error for RouteStoreError {}
+
+impl Default for RouteStore {
+    fn default() -> Self {
+        Self {
+            metas: Vec::new(),
+            name_index: HashMap::new(),
+            path_router: MatchitRouter::new(),
+            version: 0,
+        }
+    }
+}
+
+impl RouteStore {
+    /// 创建空的路由表。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册一条路由，返回分配的 RouteId。
+    ///
+    /// RouteId 由存储按注册顺序分配，会覆盖 `meta.id`。
+    /// path 中的 ":param" 段会转换为 matchit 的 "{param}" 语法。
+    pub fn insert(&mut self, mut meta: RouteMeta) -> Result<RouteId, RouteStoreError> {
+        if self.name_index.contains_key(&meta.name) {
+            return Err(RouteStoreError::DuplicateName(meta.name));
+        }
+
+        let id = RouteId(self.metas.len() as u32);
+        self.path_router
+            .insert(to_matchit_path(&meta.path), id)
+            .map_err(|e| RouteStoreError::InvalidPath {
+                path: meta.path.clone(),
+                message: e.to_string(),
+            })?;
+
+        meta.id = id;
+        self.name_index.insert(meta.name.clone(), id);
+        self.metas.push(meta);
+        self.version += 1;
+        Ok(id)
+    }
+
+    /// 根据 RouteId 获取路由元信息。
+    pub fn get(&self, id: RouteId) -> Option<&RouteMeta> {
+        self.metas.get(id.0 as usize)
+    }
+
+    /// 根据路由名获取路由元信息。
+    pub fn get_by_name(&self, name: &str) -> Option<&RouteMeta> {
+        self.name_index.get(name).and_then(|id| self.get(*id))
+    }
+
+    /// 根据路由名查找 RouteId。
+    pub fn id_of(&self, name: &str) -> Option<RouteId> {
+        self.name_index.get(name).copied()
+    }
+
+    /// 按 RouteId 顺序遍历所有路由元信息。
+    pub fn iter(&self) -> impl Iterator<Item = &RouteMeta> {
+        self.metas.iter()
+    }
+
+    /// 根据实际路径匹配路由，并提取 path 参数。
+    pub fn match_path(&self, path: &str) -> Option<ResolvedRoute<'_>> {
+        let matched = self.path_router.at(path).ok()?;
+        let id = *matched.value;
+        let path_params: BTreeMap<String, String> = matched
+            .params
+            .iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        Some(ResolvedRoute {
+            id,
+            meta: self.get(id)?,
+            path_params,
+        })
+    }
+
+    /// 路由表当前版本号。
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// 路由数量。
+    pub fn len(&self) -> usize {
+        self.metas.len()
+    }
+
+    /// 是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.metas.is_empty()
+    }
+}
+
+/// 把 "/orders/:orderId/detail" 转换为 matchit 使用的 "/orders/{orderId}/detail"。
+fn to_matchit_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{name}}}"),
+            None => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
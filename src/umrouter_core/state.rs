@@ -1,4 +1,11 @@
+mod back;
+mod behavior;
+mod machine;
 mod navigation;
+mod transaction;
 
+pub use back::*;
+pub use behavior::*;
+pub use machine::*;
 pub use navigation::*;
-
+pub use transaction::*;
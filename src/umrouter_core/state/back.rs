@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+
+use super::navigation::{RouterState, TransitionKind};
+use crate::umrouter_core::types::{RouteId, StackId};
+
+/// 返回操作的来源。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackTrigger {
+    /// Android 系统返回键 / 导航栏返回按钮。
+    #[default]
+    Button,
+
+    /// iOS 侧滑返回手势，受 `TransitionSpec::gesture_back_enabled` 约束。
+    Gesture,
+}
+
+/// 系统返回的解析配置。
+#[derive(Debug, Clone)]
+pub struct BackConfig {
+    /// 首页栈：在其他栈的 root 页面返回时切回此栈。
+    pub home_stack: StackId,
+
+    /// 返回来源。
+    pub trigger: BackTrigger,
+
+    /// 禁止通过返回离开的路由（例如支付确认页）。
+    pub blocked_routes: HashSet<RouteId>,
+}
+
+impl BackConfig {
+    pub fn new(home_stack: StackId) -> Self {
+        Self {
+            home_stack,
+            trigger: BackTrigger::Button,
+            blocked_routes: HashSet::new(),
+        }
+    }
+
+    /// 设置返回来源。
+    pub fn with_trigger(mut self, trigger: BackTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// 禁止通过返回离开某个路由。
+    pub fn with_blocked_route(mut self, route_id: RouteId) -> Self {
+        self.blocked_routes.insert(route_id);
+        self
+    }
+}
+
+/// 系统返回的解析结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackAction {
+    /// 关闭全局浮层栈的栈顶。
+    PopGlobal,
+
+    /// 弹出前台栈的栈顶。
+    Pop { stack: StackId },
+
+    /// 已在非首页栈的 root：切回首页栈。
+    SwitchToHome { stack: StackId },
+
+    /// 已在首页栈的 root：退出 / 最小化 App，由宿主处理。
+    Exit,
+
+    /// 当前可见页面禁止返回。
+    Blocked { route_id: RouteId },
+}
+
+impl BackAction {
+    /// 转换为状态机步骤；Exit 与 Blocked 不改变导航状态，返回 None。
+    pub fn to_transition(&self) -> Option<TransitionKind> {
+        match self {
+            Self::PopGlobal => Some(TransitionKind::PopGlobal),
+            Self::Pop { stack } => Some(TransitionKind::Pop {
+                target_stack: stack.clone(),
+            }),
+            Self::SwitchToHome { stack } => Some(TransitionKind::SwitchActiveStack {
+                target_stack: stack.clone(),
+                ensure_root: None,
+            }),
+            Self::Exit | Self::Blocked { .. } => None,
+        }
+    }
+}
+
+impl RouterState {
+    /// 解析系统返回（RFC §13.10）：
+    ///
+    /// 1. 全局浮层栈非空 → PopGlobal
+    /// 2. 前台栈深度 > 1 → Pop
+    /// 3. 前台栈不是首页栈（且首页栈存在）→ 切回首页栈
+    /// 4. 否则 → Exit
+    ///
+    /// 可见页面在 `blocked_routes` 中，或手势返回时其 `gesture_back_enabled` 为 false，
+    /// 返回 Blocked。
+    pub fn resolve_back(&self, config: &BackConfig) -> BackAction {
+        let Some(top) = self.visible_top() else {
+            return BackAction::Exit;
+        };
+        let gesture_blocked = config.trigger == BackTrigger::Gesture && !top.gesture_back_enabled;
+        if gesture_blocked || config.blocked_routes.contains(&top.route_id) {
+            return BackAction::Blocked {
+                route_id: top.route_id,
+            };
+        }
+
+        if !self.global_stack.is_empty() {
+            return BackAction::PopGlobal;
+        }
+        if self.active().is_some_and(|stack| stack.depth() > 1) {
+            return BackAction::Pop {
+                stack: self.active_stack.clone(),
+            };
+        }
+        if self.active_stack != config.home_stack && self.stacks.contains_key(&config.home_stack) {
+            return BackAction::SwitchToHome {
+                stack: config.home_stack.clone(),
+            };
+        }
+        BackAction::Exit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::route::RouteStore;
+    use crate::umrouter_core::state::{StackFrame, StackState, StateMachine};
+    use crate::umrouter_core::test_support::route;
+
+    #[test]
+    fn resolves_back_in_rfc_order() {
+        let mut store = RouteStore::new();
+        let home = store.insert(route("home.index", "/home", &[])).unwrap();
+        let orders = store.insert(route("orders.index", "/orders", &[])).unwrap();
+        let mut pay = route("orders.pay", "/orders/pay", &[]);
+        pay.transition_spec.gesture_back_enabled = false;
+        let pay = store.insert(pay).unwrap();
+        let login = store.insert(route("auth.login", "/login", &[])).unwrap();
+        let (home_stack, orders_stack) = (StackId("home".into()), StackId("orders".into()));
+        let frame = |id| StackFrame::from_route(store.get(id).unwrap());
+
+        let mut state = RouterState::new(StackState::new(home_stack.clone(), frame(home)));
+        let mut stack = StackState::new(orders_stack.clone(), frame(orders));
+        stack.additional_frames.push(frame(pay));
+        state.stacks.insert(orders_stack.clone(), stack);
+        state.active_stack = orders_stack.clone();
+        state.global_stack.push(frame(login));
+
+        let machine = StateMachine::new();
+        let config = BackConfig::new(home_stack.clone());
+        let mut actions = Vec::new();
+        loop {
+            let action = state.resolve_back(&config);
+            actions.push(action.clone());
+            let Some(step) = action.to_transition() else {
+                break;
+            };
+            state = machine.apply(&state, &[step], &store).unwrap().0;
+        }
+        assert_eq!(
+            actions,
+            [
+                BackAction::PopGlobal,
+                BackAction::Pop {
+                    stack: orders_stack.clone()
+                },
+                BackAction::SwitchToHome {
+                    stack: home_stack.clone()
+                },
+                BackAction::Exit,
+            ]
+        );
+
+        // 手势返回受 gesture_back_enabled 约束，blocked_routes 对按钮也生效。
+        let mut state = RouterState::new(StackState::new(orders_stack.clone(), frame(orders)));
+        state
+            .stacks
+            .get_mut(&orders_stack)
+            .unwrap()
+            .additional_frames
+            .push(frame(pay));
+        let gesture = BackConfig::new(home_stack.clone()).with_trigger(BackTrigger::Gesture);
+        assert_eq!(
+            state.resolve_back(&gesture),
+            BackAction::Blocked { route_id: pay }
+        );
+        assert!(matches!(
+            state.resolve_back(&config),
+            BackAction::Pop { .. }
+        ));
+        let blocking = BackConfig::new(home_stack).with_blocked_route(pay);
+        assert_eq!(
+            state.resolve_back(&blocking),
+            BackAction::Blocked { route_id: pay }
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::navigation::{RouterState, TransitionKind};
+use crate::umrouter_core::types::CanonicalParams;
+
+/// 多栈行为：把一次 `MultiStackOperation` 展开为基础 TransitionKind 步骤。
+///
+/// 展开时看到的是执行到该步骤时的中间状态；展开结果中也可以包含其他多栈操作。
+pub trait MultiStackBehavior: Send + Sync {
+    fn expand(&self, state: &RouterState, params: &CanonicalParams) -> Vec<TransitionKind>;
+}
+
+impl<F> MultiStackBehavior for F
+where
+    F: Fn(&RouterState, &CanonicalParams) -> Vec<TransitionKind> + Send + Sync,
+{
+    fn expand(&self, state: &RouterState, params: &CanonicalParams) -> Vec<TransitionKind> {
+        self(state, params)
+    }
+}
+
+/// 具名多栈行为表：behavior_key -> 行为。
+///
+/// `RouteKind::MultiStackRoute` 的路由通常以路由名作为 behavior_key，
+/// 例如 "logout"：重置所有栈并切回首页栈。
+#[derive(Clone, Default)]
+pub struct MultiStackBehaviorRegistry {
+    behaviors: HashMap<String, Arc<dyn MultiStackBehavior>>,
+}
+
+impl MultiStackBehaviorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册行为，返回被覆盖的同名旧行为。
+    pub fn register(
+        &mut self,
+        key: impl Into<String>,
+        behavior: impl MultiStackBehavior + 'static,
+    ) -> Option<Arc<dyn MultiStackBehavior>> {
+        self.behaviors.insert(key.into(), Arc::new(behavior))
+    }
+
+    /// 移除行为。
+    pub fn unregister(&mut self, key: &str) -> Option<Arc<dyn MultiStackBehavior>> {
+        self.behaviors.remove(key)
+    }
+
+    /// 获取行为。
+    pub fn get(&self, key: &str) -> Option<&Arc<dyn MultiStackBehavior>> {
+        self.behaviors.get(key)
+    }
+
+    /// 是否已注册该行为。
+    pub fn contains(&self, key: &str) -> bool {
+        self.behaviors.contains_key(key)
+    }
+
+    /// 行为数量。
+    pub fn len(&self) -> usize {
+        self.behaviors.len()
+    }
+
+    /// 是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.behaviors.is_empty()
+    }
+}
+
+impl fmt::Debug for MultiStackBehaviorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<_> = self.behaviors.keys().collect();
+        keys.sort();
+        f.debug_struct("MultiStackBehaviorRegistry")
+            .field("behaviors", &keys)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::route::RouteStore;
+    use crate::umrouter_core::state::{IllegalTransition, StackFrame, StackState, StateMachine};
+    use crate::umrouter_core::test_support::route;
+    use crate::umrouter_core::types::StackId;
+
+    fn logout(state: &RouterState, params: &CanonicalParams) -> Vec<TransitionKind> {
+        let home = StackId(params.map["home"].as_str().unwrap().into());
+        let mut stacks: Vec<_> = state.stacks.keys().cloned().collect();
+        stacks.sort_by(|a, b| a.0.cmp(&b.0));
+        stacks
+            .into_iter()
+            .map(|target_stack| TransitionKind::PopToRoot { target_stack })
+            .chain([TransitionKind::SwitchActiveStack {
+                target_stack: home,
+                ensure_root: None,
+            }])
+            .collect()
+    }
+
+    #[test]
+    fn expands_named_behaviors_and_records_expansion() {
+        let mut store = RouteStore::new();
+        let home = store.insert(route("home.index", "/home", &[])).unwrap();
+        let trade = store.insert(route("trade.index", "/trade", &[])).unwrap();
+        let detail = store
+            .insert(route("trade.detail", "/trade/:id", &[]))
+            .unwrap();
+        let (home_stack, trade_stack) = (StackId("home".into()), StackId("trade".into()));
+        let frame = |id| StackFrame::from_route(store.get(id).unwrap());
+
+        let mut state = RouterState::new(StackState::new(home_stack.clone(), frame(home)));
+        let mut trading = StackState::new(trade_stack.clone(), frame(trade));
+        trading.additional_frames.push(frame(detail));
+        state.stacks.insert(trade_stack.clone(), trading);
+        state.active_stack = trade_stack.clone();
+
+        let mut behaviors = MultiStackBehaviorRegistry::new();
+        behaviors.register("logout", logout);
+        behaviors.register("loop", |_: &RouterState, _: &CanonicalParams| {
+            vec![TransitionKind::MultiStackOperation {
+                behavior_key: "loop".into(),
+                params: CanonicalParams::default(),
+            }]
+        });
+        let machine = StateMachine::new().with_behaviors(behaviors);
+
+        let mut params = CanonicalParams::default();
+        params.map.insert("home".into(), "home".into());
+        let (next, transition) = machine
+            .apply(
+                &state,
+                &[TransitionKind::MultiStackOperation {
+                    behavior_key: "logout".into(),
+                    params,
+                }],
+                &store,
+            )
+            .unwrap();
+        assert_eq!(next.active_stack, home_stack);
+        assert_eq!(next.stacks[&trade_stack].depth(), 1);
+        assert_eq!(transition.steps.len(), 1);
+        let [expansion] = transition.expansions.as_slice() else {
+            panic!("expected one expansion");
+        };
+        assert_eq!((expansion.step_index, expansion.steps.len()), (0, 3));
+        assert_eq!(expansion.behavior_key, "logout");
+
+        let operation = |key: &str| TransitionKind::MultiStackOperation {
+            behavior_key: key.into(),
+            params: CanonicalParams::default(),
+        };
+        assert_eq!(
+            machine
+                .apply(&state, &[operation("missing")], &store)
+                .unwrap_err(),
+            IllegalTransition::UnknownBehavior("missing".into())
+        );
+        assert_eq!(
+            machine
+                .apply(&state, &[operation("loop")], &store)
+                .unwrap_err(),
+            IllegalTransition::BehaviorTooDeep("loop".into())
+        );
+    }
+}
@@ -0,0 +1,591 @@
+use std::fmt;
+
+use super::behavior::MultiStackBehaviorRegistry;
+use super::navigation::{
+    BehaviorExpansion, RouterState, StackFrame, StackState, Transition, TransitionKind,
+};
+use crate::umrouter_core::route::RouteStore;
+use crate::umrouter_core::types::{PresentationMode, RouteId, StackId};
+
+/// 状态机违规。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IllegalTransition {
+    /// 目标栈不存在。
+    UnknownStack(StackId),
+
+    /// 路由不在路由表中。
+    UnknownRoute(RouteId),
+
+    /// 没有可以解释该多栈操作的行为。
+    UnknownBehavior(String),
+
+    /// 多栈行为嵌套展开过深（通常是行为之间互相引用）。
+    BehaviorTooDeep(String),
+
+    /// PopToRoute 的目标路由不在栈中。
+    RouteNotInStack { stack: StackId, route: RouteId },
+
+    /// PopUntilTag 的目标栈中没有带该标签的 frame。
+    TagNotInStack { stack: StackId, tag: String },
+}
+
+impl fmt::Display for IllegalTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownStack(stack) => write!(f, "unknown stack `{}`", stack.0),
+            Self::UnknownRoute(route) => write!(f, "unknown route #{}", route.0),
+            Self::UnknownBehavior(key) => write!(f, "unknown multi-stack behavior `{key}`"),
+            Self::BehaviorTooDeep(key) => write!(
+                f,
+                "multi-stack behavior `{key}` nests deeper than {MAX_BEHAVIOR_DEPTH} levels"
+            ),
+            Self::RouteNotInStack { stack, route } => {
+                write!(f, "route #{} is not in stack `{}`", route.0, stack.0)
+            }
+            Self::TagNotInStack { stack, tag } => {
+                write!(f, "no frame tagged `{tag}` in stack `{}`", stack.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IllegalTransition {}
+
+/// 导航状态机：从旧的 RouterState 计算新的 RouterState。
+///
+/// 状态机是纯函数式的：输入状态不会被修改，任一步骤失败时整体返回错误。
+/// 各步骤语义：
+/// - Push / Pop / Replace：目标栈必须存在；Pop 在只剩 root frame 时为 no-op，
+///   Replace 在只剩 root frame 时替换 root
+/// - PopToRoot / PopCount / PopToRoute / PopUntilTag：批量弹出，root frame 始终保留；
+///   PopToRoute / PopUntilTag 找不到目标时报错
+/// - ResetStack：目标栈不存在时以 `new_root` 新建
+/// - SwitchActiveStack：目标栈不存在时用 `ensure_root` 新建，未提供则报错
+/// - MultiStackOperation：按 behavior_key 查找行为并就地展开，展开记录写入 Transition
+/// - PushGlobal / PopGlobal：操作全局浮层栈；按 GlobalOverlayConfig，
+///   Modal / Sheet 路由的 Push 也会改为压入全局浮层栈
+#[derive(Debug, Clone, Default)]
+pub struct StateMachine {
+    behaviors: MultiStackBehaviorRegistry,
+    overlay: GlobalOverlayConfig,
+}
+
+/// 哪些展示模式的路由在 Push 时进入全局浮层栈（默认都不进入）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GlobalOverlayConfig {
+    /// `PresentationMode::Modal` 的路由进入全局浮层栈。
+    pub modal: bool,
+
+    /// `PresentationMode::Sheet` 的路由进入全局浮层栈。
+    pub sheet: bool,
+}
+
+impl GlobalOverlayConfig {
+    /// 该展示模式的路由是否进入全局浮层栈。
+    pub fn applies_to(&self, presentation: PresentationMode) -> bool {
+        match presentation {
+            PresentationMode::Modal => self.modal,
+            PresentationMode::Sheet => self.sheet,
+            _ => false,
+        }
+    }
+}
+
+/// 多栈行为的最大嵌套展开深度。
+pub const MAX_BEHAVIOR_DEPTH: usize = 8;
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置多栈行为表。
+    pub fn with_behaviors(mut self, behaviors: MultiStackBehaviorRegistry) -> Self {
+        self.behaviors = behaviors;
+        self
+    }
+
+    /// 设置全局浮层栈的路由规则。
+    pub fn with_global_overlay(mut self, overlay: GlobalOverlayConfig) -> Self {
+        self.overlay = overlay;
+        self
+    }
+
+    /// 多栈行为表。
+    pub fn behaviors(&self) -> &MultiStackBehaviorRegistry {
+        &self.behaviors
+    }
+
+    /// 可修改的多栈行为表。
+    pub fn behaviors_mut(&mut self) -> &mut MultiStackBehaviorRegistry {
+        &mut self.behaviors
+    }
+
+    /// 依次应用 `steps`，返回新状态与本次变更描述。
+    pub fn apply(
+        &self,
+        state: &RouterState,
+        steps: &[TransitionKind],
+        store: &RouteStore,
+    ) -> Result<(RouterState, Transition), IllegalTransition> {
+        self.apply_indexed(state, steps, store)
+            .map_err(|(_, error)| error)
+    }
+
+    /// 同 `apply`，失败时同时返回出错的顶层步骤下标。
+    pub(super) fn apply_indexed(
+        &self,
+        state: &RouterState,
+        steps: &[TransitionKind],
+        store: &RouteStore,
+    ) -> Result<(RouterState, Transition), (usize, IllegalTransition)> {
+        let mut next = state.clone();
+        let mut expansions = Vec::new();
+        for (index, step) in steps.iter().enumerate() {
+            self.apply_step(&mut next, step, store, index, 0, &mut expansions)
+                .map_err(|error| (index, error))?;
+        }
+        let transition = Transition {
+            from_state_summary: state.summary(),
+            to_state_summary: next.summary(),
+            steps: steps.to_vec(),
+            expansions,
+        };
+        Ok((next, transition))
+    }
+
+    fn apply_step(
+        &self,
+        state: &mut RouterState,
+        step: &TransitionKind,
+        store: &RouteStore,
+        step_index: usize,
+        depth: usize,
+        expansions: &mut Vec<BehaviorExpansion>,
+    ) -> Result<(), IllegalTransition> {
+        match step {
+            TransitionKind::Push {
+                target_stack,
+                route_id,
+                presentation,
+            } => {
+                let meta = store
+                    .get(*route_id)
+                    .ok_or(IllegalTransition::UnknownRoute(*route_id))?;
+                let frame = StackFrame::from_route(meta);
+                let presentation = presentation.unwrap_or(meta.transition_spec.presentation);
+                if self.overlay.applies_to(presentation) {
+                    state.global_stack.push(frame);
+                } else {
+                    stack_mut(state, target_stack)?
+                        .additional_frames
+                        .push(frame);
+                }
+            }
+            TransitionKind::PushGlobal { route_id } => {
+                state.global_stack.push(frame(store, *route_id)?);
+            }
+            TransitionKind::PopGlobal => {
+                state.global_stack.pop();
+            }
+            TransitionKind::Pop { target_stack } => {
+                stack_mut(state, target_stack)?.additional_frames.pop();
+            }
+            TransitionKind::PopToRoot { target_stack } => {
+                stack_mut(state, target_stack)?.additional_frames.clear();
+            }
+            TransitionKind::PopCount {
+                target_stack,
+                count,
+            } => {
+                let stack = stack_mut(state, target_stack)?;
+                truncate(stack, stack.depth().saturating_sub(*count));
+            }
+            TransitionKind::PopToRoute {
+                target_stack,
+                route_id,
+                inclusive,
+            } => {
+                let stack = stack_mut(state, target_stack)?;
+                let index =
+                    position_from_top(stack, |f| f.route_id == *route_id).ok_or_else(|| {
+                        IllegalTransition::RouteNotInStack {
+                            stack: target_stack.clone(),
+                            route: *route_id,
+                        }
+                    })?;
+                truncate(stack, if *inclusive { index } else { index + 1 });
+            }
+            TransitionKind::PopUntilTag { target_stack, tag } => {
+                let stack = stack_mut(state, target_stack)?;
+                let index =
+                    position_from_top(stack, |f| f.tags.contains(tag)).ok_or_else(|| {
+                        IllegalTransition::TagNotInStack {
+                            stack: target_stack.clone(),
+                            tag: tag.clone(),
+                        }
+                    })?;
+                truncate(stack, index + 1);
+            }
+            TransitionKind::Replace {
+                target_stack,
+                route_id,
+            } => {
+                let frame = frame(store, *route_id)?;
+                let stack = stack_mut(state, target_stack)?;
+                match stack.additional_frames.last_mut() {
+                    Some(top) => *top = frame,
+                    None => stack.root_frame = frame,
+                }
+            }
+            TransitionKind::ResetStack {
+                target_stack,
+                new_root,
+            } => {
+                let root = frame(store, *new_root)?;
+                state.stacks.insert(
+                    target_stack.clone(),
+                    StackState::new(target_stack.clone(), root),
+                );
+            }
+            TransitionKind::SwitchActiveStack {
+                target_stack,
+                ensure_root,
+            } => {
+                if !state.stacks.contains_key(target_stack) {
+                    let root = ensure_root
+                        .ok_or_else(|| IllegalTransition::UnknownStack(target_stack.clone()))?;
+                    let root = frame(store, root)?;
+                    state.stacks.insert(
+                        target_stack.clone(),
+                        StackState::new(target_stack.clone(), root),
+                    );
+                }
+                state.active_stack = target_stack.clone();
+            }
+            TransitionKind::MultiStackOperation {
+                behavior_key,
+                params,
+            } => {
+                if depth >= MAX_BEHAVIOR_DEPTH {
+                    return Err(IllegalTransition::BehaviorTooDeep(behavior_key.clone()));
+                }
+                let behavior = self
+                    .behaviors
+                    .get(behavior_key)
+                    .ok_or_else(|| IllegalTransition::UnknownBehavior(behavior_key.clone()))?;
+                let expanded = behavior.expand(state, params);
+                expansions.push(BehaviorExpansion {
+                    step_index,
+                    behavior_key: behavior_key.clone(),
+                    steps: expanded.clone(),
+                });
+                for step in &expanded {
+                    self.apply_step(state, step, store, step_index, depth + 1, expansions)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn frame(store: &RouteStore, route_id: RouteId) -> Result<StackFrame, IllegalTransition> {
+    store
+        .get(route_id)
+        .map(StackFrame::from_route)
+        .ok_or(IllegalTransition::UnknownRoute(route_id))
+}
+
+/// 离栈顶最近的满足条件的 frame 下标（root 为 0）。
+fn position_from_top(stack: &StackState, pred: impl Fn(&StackFrame) -> bool) -> Option<usize> {
+    match stack.additional_frames.iter().rposition(&pred) {
+        Some(index) => Some(index + 1),
+        None => pred(&stack.root_frame).then_some(0),
+    }
+}
+
+/// 保留底部 `depth` 个 frame；root frame 始终保留。
+fn truncate(stack: &mut StackState, depth: usize) {
+    stack.additional_frames.truncate(depth.saturating_sub(1));
+}
+
+fn stack_mut<'s>(
+    state: &'s mut RouterState,
+    id: &StackId,
+) -> Result<&'s mut StackState, IllegalTransition> {
+    state
+        .stacks
+        .get_mut(id)
+        .ok_or_else(|| IllegalTransition::UnknownStack(id.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::umrouter_core::test_support::route;
+
+    fn top(state: &RouterState, stack: &str) -> RouteId {
+        state.stacks[&StackId(stack.into())].top_frame().route_id
+    }
+
+    #[test]
+    fn applies_stack_operations_atomically() {
+        let mut store = RouteStore::new();
+        let home = store.insert(route("home.index", "/home", &[])).unwrap();
+        let list = store.insert(route("orders.list", "/orders", &[])).unwrap();
+        let detail = store
+            .insert(route("orders.detail", "/orders/:id", &[]))
+            .unwrap();
+        let trade = store.insert(route("trade.index", "/trade", &[])).unwrap();
+        let main = StackId("main".into());
+        let trade_stack = StackId("trade".into());
+
+        let state = RouterState::new(StackState::new(
+            main.clone(),
+            StackFrame::from_route(store.get(home).unwrap()),
+        ));
+        let machine = StateMachine::new();
+        let (state, transition) = machine
+            .apply(
+                &state,
+                &[
+                    TransitionKind::Push {
+                        target_stack: main.clone(),
+                        route_id: list,
+                        presentation: None,
+                    },
+                    TransitionKind::Push {
+                        target_stack: main.clone(),
+                        route_id: list,
+                        presentation: None,
+                    },
+                    TransitionKind::Replace {
+                        target_stack: main.clone(),
+                        route_id: detail,
+                    },
+                    TransitionKind::SwitchActiveStack {
+                        target_stack: trade_stack.clone(),
+                        ensure_root: Some(trade),
+                    },
+                ],
+                &store,
+            )
+            .unwrap();
+        assert_eq!(state.stacks[&main].depth(), 3);
+        assert_eq!(top(&state, "main"), detail);
+        assert_eq!(state.active_stack, trade_stack);
+        assert_eq!(transition.steps.len(), 4);
+        assert_eq!(transition.from_state_summary.stack_tops.len(), 1);
+        assert_eq!(
+            transition.to_state_summary.stack_tops[&trade_stack]
+                .as_ref()
+                .unwrap()
+                .route_id,
+            trade
+        );
+
+        // Pop 在 root 处为 no-op；ResetStack 丢弃所有 frame。
+        let pop = TransitionKind::Pop {
+            target_stack: trade_stack.clone(),
+        };
+        let (next, _) = machine.apply(&state, &[pop], &store).unwrap();
+        assert_eq!(next.stacks[&trade_stack].depth(), 1);
+        let reset = TransitionKind::ResetStack {
+            target_stack: main.clone(),
+            new_root: list,
+        };
+        let (next, _) = machine.apply(&state, &[reset], &store).unwrap();
+        assert_eq!((next.stacks[&main].depth(), top(&next, "main")), (1, list));
+
+        // 任一步骤失败时返回类型化错误，输入状态不变。
+        let unknown = StackId("missing".into());
+        let steps = [
+            TransitionKind::Pop {
+                target_stack: main.clone(),
+            },
+            TransitionKind::SwitchActiveStack {
+                target_stack: unknown.clone(),
+                ensure_root: None,
+            },
+        ];
+        assert_eq!(
+            machine.apply(&state, &steps, &store).unwrap_err(),
+            IllegalTransition::UnknownStack(unknown)
+        );
+        let steps = [TransitionKind::Push {
+            target_stack: main.clone(),
+            route_id: RouteId(99),
+            presentation: None,
+        }];
+        assert_eq!(
+            machine.apply(&state, &steps, &store).unwrap_err(),
+            IllegalTransition::UnknownRoute(RouteId(99))
+        );
+        assert_eq!(state.stacks[&main].depth(), 3);
+    }
+
+    #[test]
+    fn pops_in_bulk_keeping_root() {
+        let mut store = RouteStore::new();
+        let list = store.insert(route("orders.list", "/orders", &[])).unwrap();
+        let cart = store
+            .insert(route("cart.index", "/cart", &["checkout"]))
+            .unwrap();
+        let pay = store
+            .insert(route("cart.pay", "/cart/pay", &["checkout"]))
+            .unwrap();
+        let main = StackId("main".into());
+
+        // list -> cart -> list -> cart -> pay
+        let mut stack = StackState::new(
+            main.clone(),
+            StackFrame::from_route(store.get(list).unwrap()),
+        );
+        for id in [cart, list, cart, pay] {
+            stack
+                .additional_frames
+                .push(StackFrame::from_route(store.get(id).unwrap()));
+        }
+        let state = RouterState::new(stack);
+        let machine = StateMachine::new();
+        let depth_after = |step: TransitionKind| {
+            let (next, _) = machine.apply(&state, &[step], &store).unwrap();
+            (next.stacks[&main].depth(), top(&next, "main"))
+        };
+
+        let target_stack = main.clone();
+        assert_eq!(
+            depth_after(TransitionKind::PopToRoot { target_stack }),
+            (1, list)
+        );
+        for (count, expected) in [(2, (3, list)), (10, (1, list))] {
+            let target_stack = main.clone();
+            assert_eq!(
+                depth_after(TransitionKind::PopCount {
+                    target_stack,
+                    count
+                }),
+                expected
+            );
+        }
+        for (inclusive, expected) in [(false, (4, cart)), (true, (3, list))] {
+            let step = TransitionKind::PopToRoute {
+                target_stack: main.clone(),
+                route_id: cart,
+                inclusive,
+            };
+            assert_eq!(depth_after(step), expected);
+        }
+        // root 本身是目标时，inclusive 也不会移除 root。
+        let step = TransitionKind::PopToRoute {
+            target_stack: main.clone(),
+            route_id: list,
+            inclusive: true,
+        };
+        let (next, _) = machine
+            .apply(&state, &[step.clone(), step], &store)
+            .unwrap();
+        assert_eq!(next.stacks[&main].depth(), 1);
+
+        let step = |tag: &str| TransitionKind::PopUntilTag {
+            target_stack: main.clone(),
+            tag: tag.into(),
+        };
+        assert_eq!(depth_after(step("checkout")), (5, pay));
+        assert_eq!(
+            machine
+                .apply(&state, &[step("missing")], &store)
+                .unwrap_err(),
+            IllegalTransition::TagNotInStack {
+                stack: main.clone(),
+                tag: "missing".into(),
+            }
+        );
+
+        let step = TransitionKind::PopToRoute {
+            target_stack: main.clone(),
+            route_id: RouteId(99),
+            inclusive: false,
+        };
+        assert_eq!(
+            machine.apply(&state, &[step], &store).unwrap_err(),
+            IllegalTransition::RouteNotInStack {
+                stack: main,
+                route: RouteId(99),
+            }
+        );
+    }
+
+    #[test]
+    fn routes_overlays_to_global_stack() {
+        let mut store = RouteStore::new();
+        let home = store.insert(route("home.index", "/home", &[])).unwrap();
+        let mut login = route("auth.login", "/login", &[]);
+        login.transition_spec.presentation = PresentationMode::Modal;
+        let login = store.insert(login).unwrap();
+        let mut share = route("share.panel", "/share", &[]);
+        share.transition_spec.presentation = PresentationMode::Sheet;
+        let share = store.insert(share).unwrap();
+        let main = StackId("main".into());
+        let state = RouterState::new(StackState::new(
+            main.clone(),
+            StackFrame::from_route(store.get(home).unwrap()),
+        ));
+        let push = |route_id| TransitionKind::Push {
+            target_stack: main.clone(),
+            route_id,
+            presentation: None,
+        };
+
+        // 默认不改变 Push 的目标栈。
+        let (next, _) = StateMachine::new()
+            .apply(&state, &[push(login)], &store)
+            .unwrap();
+        assert!(next.global_stack.is_empty());
+        assert_eq!(next.visible_top().unwrap().route_id, login);
+
+        let machine = StateMachine::new().with_global_overlay(GlobalOverlayConfig {
+            modal: true,
+            sheet: false,
+        });
+        let (next, transition) = machine
+            .apply(&state, &[push(login), push(share)], &store)
+            .unwrap();
+        assert_eq!(next.global_stack.len(), 1);
+        assert_eq!(top(&next, "main"), share);
+        assert_eq!(next.visible_top().unwrap().route_id, login);
+        assert_eq!(
+            transition.to_state_summary.global_top.unwrap().route_id,
+            login
+        );
+
+        let (next, _) = machine
+            .apply(
+                &next,
+                &[
+                    TransitionKind::PushGlobal { route_id: share },
+                    TransitionKind::PopGlobal,
+                    TransitionKind::PopGlobal,
+                    TransitionKind::PopGlobal,
+                ],
+                &store,
+            )
+            .unwrap();
+        assert!(next.global_stack.is_empty());
+        assert_eq!(next.visible_top().unwrap().route_id, share);
+
+        // 中间件把普通页面改写为 Modal 后，按改写后的展示模式进入全局浮层栈。
+        let detail = store
+            .insert(route("orders.detail", "/orders/:id", &[]))
+            .unwrap();
+        let rewritten = TransitionKind::Push {
+            target_stack: main.clone(),
+            route_id: detail,
+            presentation: Some(PresentationMode::Modal),
+        };
+        let (next, _) = machine.apply(&state, &[rewritten], &store).unwrap();
+        assert_eq!(next.global_stack.len(), 1);
+        assert_eq!(next.visible_top().unwrap().route_id, detail);
+        assert_eq!(top(&next, "main"), home);
+    }
+}
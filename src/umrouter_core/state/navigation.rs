@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::umrouter_core::types::{CanonicalParams, RouteId, RuntimeKind, StackId};
+use crate::umrouter_core::route::RouteMeta;
+use crate::umrouter_core::types::{
+    CanonicalParams, PresentationMode, RouteId, RuntimeKind, StackId,
+};
 
 //
 // ========== 导航状态模型：StackFrame / StackState / RouterState / Transition ==========
@@ -29,6 +32,23 @@ pub struct StackFrame {
 
     /// 扩展标签（如 "auth-flow-step-1" / "experiment-A"）。
     pub tags: Vec<String>,
+
+    /// 是否允许通过侧滑手势离开此页面（取自路由的 TransitionSpec）。
+    pub gesture_back_enabled: bool,
+}
+
+impl StackFrame {
+    /// 为路由创建页面实例：runtime 与标签取自路由元信息。
+    pub fn from_route(meta: &RouteMeta) -> Self {
+        Self {
+            route_id: meta.id,
+            runtime: meta.runtime,
+            params_snapshot: None,
+            opened_at_millis: None,
+            tags: meta.tags.clone(),
+            gesture_back_enabled: meta.transition_spec.gesture_back_enabled,
+        }
+    }
 }
 
 /// 一条"业务导航栈"的状态。
@@ -56,6 +76,15 @@ pub struct StackState {
 }
 
 impl StackState {
+    /// 以 root frame 创建栈。
+    pub fn new(id: StackId, root_frame: StackFrame) -> Self {
+        Self {
+            id,
+            root_frame,
+            additional_frames: Vec::new(),
+        }
+    }
+
     /// 获取所有 frames（包括 root），从底到顶。
     pub fn all_frames(&self) -> impl Iterator<Item = &StackFrame> {
         std::iter::once(&self.root_frame).chain(self.additional_frames.iter())
@@ -109,10 +138,58 @@ pub struct RouterState {
     /// - 栈本身的 frames 通常被保留，只是 active 的栈变了。
     pub active_stack: StackId,
 
+    /// 全局浮层栈（RFC 中的 `_global`）：登录弹窗、分享面板等，覆盖在所有业务栈之上。
+    ///
+    /// 与业务栈不同，全局栈可以为空（从底到顶）。
+    pub global_stack: Vec<StackFrame>,
+
     /// 当前可见的 runtime 实例层级信息（可选扩展）。
     pub runtime_layers: Vec<RuntimeLayer>,
 }
 
+impl RouterState {
+    /// 以一个前台栈创建初始状态。
+    pub fn new(active: StackState) -> Self {
+        let active_stack = active.id.clone();
+        Self {
+            stacks: HashMap::from([(active_stack.clone(), active)]),
+            active_stack,
+            global_stack: Vec::new(),
+            runtime_layers: Vec::new(),
+        }
+    }
+
+    /// 获取指定栈。
+    pub fn stack(&self, id: &StackId) -> Option<&StackState> {
+        self.stacks.get(id)
+    }
+
+    /// 当前前台栈。
+    pub fn active(&self) -> Option<&StackState> {
+        self.stacks.get(&self.active_stack)
+    }
+
+    /// 用户当前看到的页面：全局浮层栈非空时为其栈顶，否则为前台栈的栈顶。
+    pub fn visible_top(&self) -> Option<&StackFrame> {
+        self.global_stack
+            .last()
+            .or_else(|| self.active().map(StackState::top_frame))
+    }
+
+    /// 生成状态摘要。
+    pub fn summary(&self) -> StateSummary {
+        StateSummary {
+            active_stack: self.active_stack.clone(),
+            stack_tops: self
+                .stacks
+                .iter()
+                .map(|(id, stack)| (id.clone(), Some(StackTopSnapshot::of(stack.top_frame()))))
+                .collect(),
+            global_top: self.global_stack.last().map(StackTopSnapshot::of),
+        }
+    }
+}
+
 /// 导航变更的"操作类型"描述。
 ///
 /// 状态机会根据当前 RouterState + RouteMeta + NavAction 请求，
@@ -120,9 +197,13 @@ pub struct RouterState {
 #[derive(Debug, Clone)]
 pub enum TransitionKind {
     /// 在指定栈上执行 Push。
+    ///
+    /// `presentation` 为 pipeline 改写后的展示模式，为空时使用路由 TransitionSpec 中的配置；
+    /// 它决定页面进入目标栈还是全局浮层栈。
     Push {
         target_stack: StackId,
         route_id: RouteId,
+        presentation: Option<PresentationMode>,
     },
 
     /// 在指定栈上执行 Pop（从栈顶移除一个 frame）。
@@ -132,6 +213,31 @@ pub enum TransitionKind {
     ///       如果 additional_frames 为空，Pop 为 no-op。
     Pop { target_stack: StackId },
 
+    /// 在全局浮层栈上执行 Push。
+    PushGlobal { route_id: RouteId },
+
+    /// 弹出全局浮层栈的栈顶；全局栈为空时为 no-op。
+    PopGlobal,
+
+    /// 弹出 root 之上的所有 frame。
+    PopToRoot { target_stack: StackId },
+
+    /// 从栈顶弹出 `count` 个 frame，最多弹到只剩 root。
+    PopCount { target_stack: StackId, count: usize },
+
+    /// 弹回离栈顶最近的 `route_id` 页面。
+    ///
+    /// - `inclusive` 为 false 时该页面成为新的栈顶
+    /// - `inclusive` 为 true 时该页面也一并弹出；该页面是 root 时保留 root
+    PopToRoute {
+        target_stack: StackId,
+        route_id: RouteId,
+        inclusive: bool,
+    },
+
+    /// 持续弹出，直到栈顶 frame 带有 `tag`；没有任何 frame 带该标签时报 `TagNotInStack`。
+    PopUntilTag { target_stack: StackId, tag: String },
+
     /// 在指定栈上执行 Replace（用新的 route 替换栈顶）。
     Replace {
         target_stack: StackId,
@@ -153,12 +259,16 @@ pub enum TransitionKind {
         ensure_root: Option<RouteId>,
     },
 
-    /// 多栈操作（预留用于以后更复杂的场景）。
+    /// 多栈操作。
     ///
-    /// 比如同时重置多个栈、按照某个策略批量迁移栈内容等。
+    /// 比如同时重置多个栈、按照某个策略批量迁移栈内容等；
+    /// 由状态机通过 MultiStackBehaviorRegistry 展开为基础步骤。
     MultiStackOperation {
         /// 业务定义的多栈行为 key / 类型，用于在状态机中做具体解释。
         behavior_key: String,
+
+        /// 传给行为的参数。
+        params: CanonicalParams,
     },
 }
 
@@ -169,6 +279,15 @@ pub struct StackTopSnapshot {
     pub runtime: RuntimeKind,
 }
 
+impl StackTopSnapshot {
+    fn of(frame: &StackFrame) -> Self {
+        Self {
+            route_id: frame.route_id,
+            runtime: frame.runtime,
+        }
+    }
+}
+
 /// 为了避免在 Transition 中完整拷贝 RouterState，
 /// 提供一个轻量的状态摘要结构，用于日志、调试和命令生成参考。
 #[derive(Debug, Clone)]
@@ -178,6 +297,9 @@ pub struct StateSummary {
 
     /// 各栈的栈顶 route 概览（如果存在）。
     pub stack_tops: HashMap<StackId, Option<StackTopSnapshot>>,
+
+    /// 全局浮层栈的栈顶（为空时为 None）。
+    pub global_top: Option<StackTopSnapshot>,
 }
 
 /// 一次完整导航操作导致的"状态变更描述"。
@@ -199,4 +321,20 @@ pub struct Transition {
     ///
     /// 例如一个复杂的操作可能先 Reset 再 Push，再 SwitchActiveStack。
     pub steps: Vec<TransitionKind>,
+
+    /// 多栈操作的展开记录（按展开顺序），用于调试。
+    pub expansions: Vec<BehaviorExpansion>,
+}
+
+/// 一次多栈操作的展开记录。
+#[derive(Debug, Clone)]
+pub struct BehaviorExpansion {
+    /// 所属的顶层步骤下标（`Transition::steps` 中的位置）。
+    pub step_index: usize,
+
+    /// 展开的行为 key。
+    pub behavior_key: String,
+
+    /// 展开得到的步骤。
+    pub steps: Vec<TransitionKind>,
 }
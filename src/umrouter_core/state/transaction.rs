@@ -0,0 +1,259 @@
+use std::fmt;
+
+use super::machine::{IllegalTransition, StateMachine};
+use super::navigation::{RouterState, Transition, TransitionKind};
+use crate::umrouter_core::middleware::MiddlewareRegistry;
+use crate::umrouter_core::route::RouteStore;
+use crate::umrouter_core::types::{CanonicalParams, RouteId};
+
+/// 事务步骤失败的原因。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepError {
+    /// 状态机拒绝该步骤。
+    Illegal(IllegalTransition),
+
+    /// 步骤携带的参数未通过目标路由的参数校验。
+    InvalidParams(String),
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Illegal(error) => error.fmt(f),
+            Self::InvalidParams(detail) => write!(f, "invalid params: {detail}"),
+        }
+    }
+}
+
+/// 事务中某一步失败。
+#[derive(Debug, Clone)]
+pub struct TransactionError {
+    /// 失败步骤在事务中的下标。
+    pub step_index: usize,
+
+    /// 失败的步骤。
+    pub step: TransitionKind,
+
+    /// 失败原因。
+    pub error: StepError,
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "navigation transaction failed at step {} ({:?}): {}",
+            self.step_index, self.step, self.error
+        )
+    }
+}
+
+impl std::error::Error for TransactionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.error {
+            StepError::Illegal(error) => Some(error),
+            StepError::InvalidParams(_) => None,
+        }
+    }
+}
+
+/// 导航事务：一组要么全部生效、要么全部不生效的 TransitionKind 步骤。
+///
+/// 提交时在临时状态上依次应用各步骤；只有全部成功才返回新状态，
+/// 任一步骤失败时原状态保持不变，错误中带上失败的步骤。
+///
+/// 用 `with_validated_step` 加入的步骤会在 `commit_validated` 中按目标路由的
+/// `ParamSchemaSpec` 校验参数（与参数校验核心中间件使用同一份 schema 表）；
+/// `commit` 只检查状态机错误。
+#[derive(Debug, Clone, Default)]
+pub struct NavTransaction {
+    steps: Vec<TransitionKind>,
+
+    /// 与 steps 一一对应：需要校验的参数。
+    params: Vec<Option<CanonicalParams>>,
+}
+
+impl NavTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个步骤。
+    pub fn with_step(mut self, step: TransitionKind) -> Self {
+        self.push(step);
+        self
+    }
+
+    /// 追加一个步骤。
+    pub fn push(&mut self, step: TransitionKind) {
+        self.steps.push(step);
+        self.params.push(None);
+    }
+
+    /// 追加一个需要校验参数的步骤。
+    ///
+    /// 只有打开路由的步骤（Push / PushGlobal / Replace / ResetStack，
+    /// 以及带 `ensure_root` 的 SwitchActiveStack）会校验参数。
+    pub fn with_validated_step(mut self, step: TransitionKind, params: CanonicalParams) -> Self {
+        self.steps.push(step);
+        self.params.push(Some(params));
+        self
+    }
+
+    /// 已加入的步骤。
+    pub fn steps(&self) -> &[TransitionKind] {
+        &self.steps
+    }
+
+    /// 步骤数量。
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// 是否为空。
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// 提交事务，成功时返回新状态与包含全部步骤的一次 Transition。
+    ///
+    /// 不校验参数，需要校验时使用 `commit_validated`。
+    pub fn commit(
+        &self,
+        machine: &StateMachine,
+        state: &RouterState,
+        store: &RouteStore,
+    ) -> Result<(RouterState, Transition), TransactionError> {
+        machine
+            .apply_indexed(state, &self.steps, store)
+            .map_err(|(step_index, error)| self.error(step_index, StepError::Illegal(error)))
+    }
+
+    /// 校验各步骤携带的参数后提交事务；报告最先失败的步骤。
+    pub fn commit_validated(
+        &self,
+        machine: &StateMachine,
+        state: &RouterState,
+        store: &RouteStore,
+        registry: &MiddlewareRegistry,
+    ) -> Result<(RouterState, Transition), TransactionError> {
+        let invalid =
+            self.steps
+                .iter()
+                .zip(&self.params)
+                .enumerate()
+                .find_map(|(index, (step, params))| {
+                    let route = store.get(opened_route(step)?)?;
+                    let error = registry.validate_params(route, params.as_ref()?).err()?;
+                    Some((index, error))
+                });
+        match (self.commit(machine, state, store), invalid) {
+            (Err(e), Some((index, _))) if e.step_index < index => Err(e),
+            (_, Some((index, detail))) => Err(self.error(index, StepError::InvalidParams(detail))),
+            (result, None) => result,
+        }
+    }
+
+    fn error(&self, step_index: usize, error: StepError) -> TransactionError {
+        TransactionError {
+            step_index,
+            step: self.steps[step_index].clone(),
+            error,
+        }
+    }
+}
+
+/// 步骤打开的路由。
+fn opened_route(step: &TransitionKind) -> Option<RouteId> {
+    match step {
+        TransitionKind::Push { route_id, .. }
+        | TransitionKind::PushGlobal { route_id }
+        | TransitionKind::Replace { route_id, .. } => Some(*route_id),
+        TransitionKind::ResetStack { new_root, .. } => Some(*new_root),
+        TransitionKind::SwitchActiveStack { ensure_root, .. } => *ensure_root,
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::umrouter_core::state::{StackFrame, StackState};
+    use crate::umrouter_core::test_support::route;
+    use crate::umrouter_core::types::{RouteId, StackId};
+
+    #[test]
+    fn commits_all_steps_or_nothing() {
+        let mut store = RouteStore::new();
+        let home = store.insert(route("home.index", "/home", &[])).unwrap();
+        let trade = store.insert(route("trade.index", "/trade", &[])).unwrap();
+        let mut order = route("trade.order", "/trade/order/:id", &[]);
+        order.param_schema.schema_id = Some("order".into());
+        let detail = store.insert(order).unwrap();
+        let (home_stack, trade_stack) = (StackId("home".into()), StackId("trade".into()));
+        let state = RouterState::new(StackState::new(
+            home_stack.clone(),
+            StackFrame::from_route(store.get(home).unwrap()),
+        ));
+        let machine = StateMachine::new();
+
+        let switch_to_trade = || {
+            NavTransaction::new()
+                .with_step(TransitionKind::ResetStack {
+                    target_stack: trade_stack.clone(),
+                    new_root: trade,
+                })
+                .with_step(TransitionKind::SwitchActiveStack {
+                    target_stack: trade_stack.clone(),
+                    ensure_root: None,
+                })
+        };
+        let push = |route_id| TransitionKind::Push {
+            target_stack: trade_stack.clone(),
+            route_id,
+            presentation: None,
+        };
+        let open_order = |route_id| switch_to_trade().with_step(push(route_id));
+
+        let (next, transition) = open_order(detail).commit(&machine, &state, &store).unwrap();
+        assert_eq!(next.active_stack, trade_stack);
+        assert_eq!(next.stacks[&trade_stack].depth(), 2);
+        assert_eq!(transition.steps.len(), 3);
+
+        let missing = RouteId(999);
+        let err = open_order(missing)
+            .commit(&machine, &state, &store)
+            .unwrap_err();
+        assert_eq!(err.step_index, 2);
+        assert_eq!(
+            err.error,
+            StepError::Illegal(IllegalTransition::UnknownRoute(missing))
+        );
+        assert!(matches!(err.step, TransitionKind::Push { .. }));
+        // 原状态未受前两步影响。
+        assert_eq!(state.active_stack, home_stack);
+        assert!(!state.stacks.contains_key(&trade_stack));
+
+        // 第三步参数校验失败时同样整体不生效。
+        let mut registry = MiddlewareRegistry::new();
+        registry
+            .register_schema("order", &json!({"type": "object", "required": ["id"]}))
+            .unwrap();
+        let open_validated =
+            |params: CanonicalParams| switch_to_trade().with_validated_step(push(detail), params);
+        let err = open_validated(CanonicalParams::default())
+            .commit_validated(&machine, &state, &store, &registry)
+            .unwrap_err();
+        assert_eq!(err.step_index, 2);
+        assert!(matches!(err.error, StepError::InvalidParams(_)));
+
+        let mut params = CanonicalParams::default();
+        params.map.insert("id".into(), json!("42"));
+        let (next, _) = open_validated(params)
+            .commit_validated(&machine, &state, &store, &registry)
+            .unwrap();
+        assert_eq!(next.stacks[&trade_stack].depth(), 2);
+    }
+}
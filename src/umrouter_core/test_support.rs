@@ -0,0 +1,127 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use crate::umrouter_core::middleware::{
+    Clock, ExecuteContext, Executor, Extensions, FnExecutor, Matcher, Middleware,
+    MiddlewareExecutor, MiddlewarePhase, MiddlewareRegistry, MiddlewareResult,
+};
+use crate::umrouter_core::pipeline::{NavigationContext, PipelineResult, PipelineRunner};
+use crate::umrouter_core::route::{
+    HookSpec, ParamSchemaSpec, RouteKind, RouteMeta, TransitionSpec,
+};
+use crate::umrouter_core::types::{
+    CanonicalParams, MiddlewareId, PresentationMode, RouteId, RuntimeKind, StackId,
+};
+
+/// 构造测试用的路由元信息。
+pub(crate) fn route(name: &str, path: &str, tags: &[&str]) -> RouteMeta {
+    RouteMeta {
+        id: RouteId(0),
+        path: path.into(),
+        name: name.into(),
+        runtime: RuntimeKind::Native,
+        preferred_stack: StackId("main".into()),
+        route_kind: RouteKind::StackRoute,
+        param_schema: ParamSchemaSpec {
+            schema_id: None,
+            has_sub_schemas: false,
+        },
+        hook_spec: HookSpec {
+            enabled_lifecycles: vec![],
+            custom_hooks: vec![],
+        },
+        transition_spec: TransitionSpec {
+            presentation: PresentationMode::Push,
+            animation: None,
+            gesture_back_enabled: true,
+        },
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+    }
+}
+
+/// 构造测试用的中间件。
+pub(crate) fn middleware(
+    id: &str,
+    phase: MiddlewarePhase,
+    priority: i32,
+    matcher: impl Matcher + 'static,
+    executor: impl Executor + 'static,
+) -> Middleware {
+    Middleware::new(
+        MiddlewareId(id.into()),
+        Arc::new(matcher),
+        MiddlewareExecutor::sync(executor),
+        phase,
+    )
+    .with_priority(priority)
+}
+
+/// 直接返回 Continue 的 executor。
+pub(crate) fn noop() -> impl Executor {
+    FnExecutor::new("noop", |_: &mut ExecuteContext| MiddlewareResult::Continue)
+}
+
+/// 最简单的本地 executor：在当前线程上驱动 future 直到完成。
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// 以 `params` 导航到 `meta` 并执行解析出的 PreRW 链，
+/// 返回执行结果与执行后的扩展数据。
+pub(crate) fn run_pre_rw(
+    registry: &MiddlewareRegistry,
+    meta: &RouteMeta,
+    params: CanonicalParams,
+) -> (PipelineResult, Extensions) {
+    let mut nav = NavigationContext::new(meta, params);
+    let chain = registry.resolve_chain(&nav.match_context()).pre_rw_chain;
+    let result = block_on(PipelineRunner::new(registry).run(&chain, &mut nav)).result;
+    (result, nav.extensions)
+}
+
+/// 手动推进的时钟。
+#[derive(Debug)]
+pub(crate) struct ManualClock {
+    base: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// 向前推进时间。
+    pub(crate) fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
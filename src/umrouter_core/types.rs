@@ -26,7 +26,7 @@ pub enum RuntimeKind {
 ///
 /// 代表 path/query/body merge 后的统一参数视图。
 /// 使用 serde_json::Value 支持复杂的参数结构（数组、嵌套对象等）。
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CanonicalParams {
     pub map: BTreeMap<String, Value>,
 }
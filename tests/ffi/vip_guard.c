@@ -0,0 +1,48 @@
+/*
+ * 用 C 实现的 VIP 守卫，供 middleware::ffi 的测试使用：
+ * - matcher：路由带有 "vip" 标签时匹配
+ * - executor：member 为 gold 时放行并写入扩展数据，否则重定向到升级页
+ */
+#include <stdlib.h>
+#include <string.h>
+
+#include "umrouter.h"
+
+static bool vip_matches(void *user_data, const char *context_json) {
+    (void)user_data;
+    return strstr(context_json, "\"tags\":[\"vip\"") != NULL;
+}
+
+static char *vip_execute(void *user_data, const char *context_json) {
+    const char *result;
+    (void)user_data;
+    if (strstr(context_json, "\"member\":\"gold\"") != NULL) {
+        result = "{\"result\":\"continue\",\"extensions\":{\"vip.checked\":\"gold\"}}";
+    } else {
+        result = "{\"result\":\"redirect\",\"request\":{\"name\":\"vip.upgrade\"}}";
+    }
+    char *out = malloc(strlen(result) + 1);
+    if (out != NULL) {
+        strcpy(out, result);
+    }
+    return out;
+}
+
+static void vip_free_result(void *user_data, char *result) {
+    (void)user_data;
+    free(result);
+}
+
+static void count_drop(void *user_data) {
+    *(int *)user_data += 1;
+}
+
+UmMatcherVTable um_fixture_vip_matcher(int *drops) {
+    UmMatcherVTable vtable = {drops, vip_matches, count_drop, "vip_matcher"};
+    return vtable;
+}
+
+UmExecutorVTable um_fixture_vip_executor(int *drops) {
+    UmExecutorVTable vtable = {drops, vip_execute, vip_free_result, count_drop, "vip_guard"};
+    return vtable;
+}